@@ -44,8 +44,8 @@ fn expense_report(input: &str, n_candidates: usize) -> Option<i32> {
     */
 
     // first, let's parse and sort our input
-    let mut entries = input.lines()
-        .map(|line| line.parse::<i32>().unwrap())
+    let mut entries = lib::input::tidy(input).numbers::<i32>()
+        .map(|entry| entry.unwrap().1)
         .collect::<Vec<_>>();
     entries.sort_unstable();
 
@@ -92,4 +92,26 @@ mod tests {
         let answer = expense_report(EXAMPLE_INPUT, 3).unwrap();
         assert_eq!(answer, 241861950);
     }
+
+    #[test]
+    fn pairs_agree_with_brute_force() {
+        use lib::check::*;
+
+        // small entries, so plenty of lists have a pair that adds up to 2020, and some have more than one
+        check(vecs(ints(0..=2020), 0..=30), |entries: &Vec<i32>| {
+            let mut products = Vec::new();
+            for i in 0..entries.len() {
+                for j in i + 1..entries.len() {
+                    if entries[i] + entries[j] == 2020 {
+                        products.push(entries[i] * entries[j]);
+                    }
+                }
+            }
+            let input = entries.iter().map(|n| format!("{}\n", n)).collect::<String>();
+            match expense_report(&input, 2) {
+                Some(product) => products.contains(&product),
+                None => products.is_empty(),
+            }
+        });
+    }
 }
\ No newline at end of file
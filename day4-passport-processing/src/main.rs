@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use lib::input::tidy;
+use lib::parse::{one_of_map, OneOf};
+use lib::records::*;
+use lib::validate::*;
 
 fn main() {
     let input = include_str!("input.txt");
@@ -16,38 +19,57 @@ fn main() {
     Treat cid as optional. In your batch file, how many passports are valid?
 */
 fn part1(input: &str) -> usize {
-    use lib::parse::*;
-
-    string(character().repeat(3)).map_into::<FieldName>()
-    .skip(":")
-    .then(string(character().up_until(whitespace())))
-    .repeat_delimited(Many, " ".or("\n"))
-    .repeat_delimited(Many, "\n\n")
-    .parse_result(input).unwrap()
-    .into_iter()
-    .filter(is_valid_passport)
-    .count()
-}
-
-#[allow(clippy::ptr_arg)]
-fn is_valid_passport(passport: &Vec<(FieldName, String)>) -> bool {
     use FieldName::*;
-    let required = [BirthYear, IssueYear, ExpirationYear, Height, HairColor, EyeColor, PassportId];
-    let mut matches = required.iter().map(|f| (f, false)).collect::<HashMap<_,_>>();
-    for (field, _) in passport {
-        *matches.entry(field).or_default() = true;
-    }
-    matches.iter().all(|(_, found)| *found)
+    let schema = [BirthYear, IssueYear, ExpirationYear, Height, HairColor, EyeColor, PassportId].iter()
+        .fold(Schema::new(), |schema, field| schema.required(field.clone(), Rule::Anything));
+    count_valid_passports(input, &schema)
 }
 
 /** Part 2:
+    Now every field has to be valid, too:
+    - byr (Birth Year) - four digits; at least 1920 and at most 2002.
+    - iyr (Issue Year) - four digits; at least 2010 and at most 2020.
+    - eyr (Expiration Year) - four digits; at least 2020 and at most 2030.
+    - hgt (Height) - a number followed by either cm or in: 150-193cm, or 59-76in.
+    - hcl (Hair Color) - a # followed by exactly six characters 0-9 or a-f.
+    - ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
+    - pid (Passport ID) - a nine-digit number, including leading zeroes.
+    - cid (Country ID) - ignored, missing or not.
 
+    Count the number of valid passports - those that have all required fields and valid values.
 */
 fn part2(input: &str) -> usize {
-    todo!()
+    use FieldName::*;
+    let schema = Schema::new()
+        .required(BirthYear, Rule::range(1920..=2002))
+        .required(IssueYear, Rule::range(2010..=2020))
+        .required(ExpirationYear, Rule::range(2020..=2030))
+        .required(Height, Rule::measure(150..=193, "cm").or(Rule::measure(59..=76, "in")))
+        .required(HairColor, Rule::matches("#[0-9a-f]{6}"))
+        .required(EyeColor, Rule::one_of(&["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]))
+        .required(PassportId, Rule::matches(r"\d{9}"));
+    count_valid_passports(input, &schema)
+}
+
+fn count_valid_passports(input: &str, schema: &Schema<FieldName>) -> usize {
+    use lib::parse::Parser;
+
+    let field_names = field_names();
+    let passport = Records::new().record();
+    tidy(input).groups()
+        .map(|(line, text)| passport.parse_complete(text).unwrap_or_else(|e| panic!("passport on line {}:\n{}", line, e)))
+        .filter(|passport| check_passport(passport, schema, &field_names).is_valid())
+        .count()
+}
+
+fn check_passport<'a>(passport: &Record<'a>, schema: &Schema<FieldName>, field_names: &OneOf<FieldName>) -> Report<'a, FieldName> {
+    schema.check(passport.iter().map(|field| {
+        let name = field_names.get(field.key).cloned().unwrap_or_else(|| FieldName::Unknown(field.key.to_string()));
+        (name, field.value)
+    }))
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum FieldName {
     BirthYear,
     IssueYear,
@@ -59,21 +81,19 @@ enum FieldName {
     CountryId,
     Unknown(String),
 }
-impl From<String> for FieldName {
-    fn from(s: String) -> Self {
-        use FieldName::*;
-        match s.as_str() {
-            "byr" => BirthYear,
-            "iyr" => IssueYear,
-            "eyr" => ExpirationYear,
-            "hgt" => Height,
-            "hcl" => HairColor,
-            "ecl" => EyeColor,
-            "pid" => PassportId,
-            "cid" => CountryId,
-            _ => Unknown(s),
-        }
-    }
+
+fn field_names() -> OneOf<FieldName> {
+    use FieldName::*;
+    one_of_map([
+        ("byr", BirthYear),
+        ("iyr", IssueYear),
+        ("eyr", ExpirationYear),
+        ("hgt", Height),
+        ("hcl", HairColor),
+        ("ecl", EyeColor),
+        ("pid", PassportId),
+        ("cid", CountryId),
+    ])
 }
 
 #[cfg(test)]
@@ -87,10 +107,30 @@ mod tests {
         assert_eq!(expected, part1(input));
     }
 
+    #[test]
+    fn part1_example1_crlf() {
+        let input = include_str!("example1.txt").replace('\n', "\r\n") + "\r\n\r\n";
+        assert_eq!(2, part1(&input));
+    }
+
     #[test]
     fn part2_example1() {
         let input = include_str!("example1.txt");
+        let expected = 2;
+        assert_eq!(expected, part2(input));
+    }
+
+    #[test]
+    fn part2_example2_all_invalid() {
+        let input = include_str!("example2.txt");
         let expected = 0;
         assert_eq!(expected, part2(input));
     }
+
+    #[test]
+    fn part2_example3_all_valid() {
+        let input = include_str!("example3.txt");
+        let expected = 4;
+        assert_eq!(expected, part2(input));
+    }
 }
\ No newline at end of file
@@ -47,15 +47,10 @@ fn parse_and_sort(input: &str) -> Vec<usize> {
     //       = seat
     //
     // and also note that nothing in the problem actually requires row/col coordinates, just id
+    use lib::parse::*;
+    let seat = binary_with::<usize>("FL", "BR");
     let mut passes = input.lines()
-        .map(|l| {
-            let l = l
-                .replace("F", "0")
-                .replace("B", "1")
-                .replace("L", "0")
-                .replace("R", "1");
-            usize::from_str_radix(&l, 2).unwrap()
-        })
+        .map(|l| seat.parse_complete(l).unwrap())
         .collect::<Vec<_>>();
     passes.sort_unstable();
     
@@ -1,5 +1,3 @@
-use std::collections::{HashMap, HashSet};
-
 fn main() {
     let input = include_str!("input.txt");
     println!("Part 1 Answer: {}", part1(input));
@@ -12,35 +10,26 @@ fn main() {
 
 */
 fn part1(input: &str) -> usize {
-    input.split("\n\n")
-        .map(|s| {
-            s.chars()
-                .filter(|c| !c.is_whitespace())
-                .collect::<HashSet<_>>()
-                .len()
-        })
-        .sum()
+    count_answers(input, |(anyone, _everyone)| anyone)
 }
 
 /** Part 2:
 
 */
 fn part2(input: &str) -> usize {
-    input.split("\n\n")
-        .map(|group| {
-            let n_people = group.lines().count();
+    count_answers(input, |(_anyone, everyone)| everyone)
+}
 
-            group.chars()
-                .filter(|c| !c.is_whitespace())
-                .fold(HashMap::new(), |map, ans| {
-                    let mut map = map;
-                    *map.entry(ans).or_insert(0) += 1;
-                    map
-                })
-                .into_iter()
-                .filter(|(_q, n_ans)| *n_ans == n_people)
-                .count()
-        })
+// totals up the questions picked out of each group by `which`, from the ones anyone in the group answered,
+// and the ones everyone did. a set of questions is a bit each for a through z, so nothing gets allocated
+fn count_answers(input: &str, which: fn((u32, u32)) -> u32) -> usize {
+    use lib::parse::*;
+    let person = character().filter(char::is_ascii_lowercase).named("answer")
+        .fold_many(Many, 0u32, |answers, c| answers | 1 << (c as u32 - 'a' as u32));
+    let group = person.skip("\n".or(""))
+        .fold_many(Many, (0, u32::MAX), |(anyone, everyone), answers| (anyone | answers, everyone & answers));
+    lib::input::tidy(input).groups()
+        .map(|(_line, text)| which(group.parse_complete(text).unwrap()).count_ones() as usize)
         .sum()
 }
 
@@ -0,0 +1,438 @@
+/*
+    #[derive(Parse)], which turns a format string like "{n1}-{n2} {letter}: {password}" into a lib::parse
+    parser (and a FromStr impl on top of it). see lib::parse::Parse for what it looks like from the outside.
+
+    this deliberately doesn't pull in syn or quote, so it picks the item apart by hand. that's less work than it
+    sounds, since all we care about is the name, the fields and their types, and the #[parse] attributes. the
+    generated code gets built up as a string and turned back into tokens at the very end, which is crude, but
+    it's a few dozen lines of output and nobody has to read it.
+*/
+use std::iter::Peekable;
+
+use proc_macro::{token_stream, Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+#[proc_macro_derive(Parse, attributes(parse))]
+pub fn derive_parse(input: TokenStream) -> TokenStream {
+    match read_item(input) {
+        Ok(item) => match generate(&item) {
+            Ok(code) => code.parse().expect("generated code is always valid rust"),
+            Err(e) => e.into_compile_error(),
+        },
+        Err(e) => e.into_compile_error(),
+    }
+}
+
+struct Error {
+    span: Span,
+    message: String,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into() }
+    }
+
+    // compile_error! { "message" }, with every token pointing at the problem, so that's what gets underlined
+    fn into_compile_error(self) -> TokenStream {
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.span);
+        let mut message = Literal::string(&self.message);
+        message.set_span(self.span);
+        let mut body = Group::new(Delimiter::Brace, TokenTree::from(message).into());
+        body.set_span(self.span);
+        vec![
+            TokenTree::from(Ident::new("compile_error", self.span)),
+            bang.into(),
+            body.into(),
+        ].into_iter().collect()
+    }
+}
+
+struct Item {
+    name: String,
+    kind: Kind,
+}
+
+enum Kind {
+    Struct(Variant),
+    Enum(Vec<Variant>),
+}
+
+/// a struct, or one variant of an enum: what we need to parse, and how to build it afterwards
+struct Variant {
+    name: String,
+    span: Span,
+    format: Option<(String, Span)>,
+    fields: Fields,
+}
+
+enum Fields {
+    Named(Vec<Field>),
+    Tuple(Vec<Field>),
+    Unit,
+}
+
+struct Field {
+    /// how the format refers to it: the field name, or its index for tuple fields
+    name: String,
+    /// the field name as it was written, which is only different for raw identifiers like `r#type`
+    ident: String,
+    ty: String,
+}
+
+impl Fields {
+    fn all(&self) -> &[Field] {
+        match self {
+            Fields::Named(fields) | Fields::Tuple(fields) => fields,
+            Fields::Unit => &[],
+        }
+    }
+}
+
+type Tokens = Peekable<token_stream::IntoIter>;
+
+fn read_item(input: TokenStream) -> Result<Item> {
+    let mut tokens = input.into_iter().peekable();
+    let format = read_attributes(&mut tokens)?;
+    skip_visibility(&mut tokens);
+
+    let keyword = expect_ident(&mut tokens, Span::call_site())?;
+    let name = expect_ident(&mut tokens, keyword.span())?;
+    if let Some(TokenTree::Punct(p)) = tokens.peek() {
+        if p.as_char() == '<' {
+            return Err(Error::new(p.span(), "#[derive(Parse)] doesn't support generic types"));
+        }
+    }
+
+    let kind = match keyword.to_string().as_str() {
+        "struct" => Kind::Struct(Variant {
+            name: name.to_string(),
+            span: name.span(),
+            format,
+            fields: read_fields(tokens.next())?,
+        }),
+        "enum" => {
+            if let Some((_, span)) = format {
+                return Err(Error::new(span, "an enum gets one #[parse] format per variant, not one for the whole thing"));
+            }
+            match tokens.next() {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => Kind::Enum(read_variants(g.stream())?),
+                _ => return Err(Error::new(name.span(), "expected the enum's variants")),
+            }
+        }
+        _ => return Err(Error::new(keyword.span(), "#[derive(Parse)] only works on structs and enums")),
+    };
+    Ok(Item { name: name.to_string(), kind })
+}
+
+// reads any attributes off the front of `tokens`, and returns the format from #[parse("...")] if there was one
+fn read_attributes(tokens: &mut Tokens) -> Result<Option<(String, Span)>> {
+    let mut format = None;
+    while let Some(TokenTree::Punct(p)) = tokens.peek() {
+        if p.as_char() != '#' {
+            break;
+        }
+        let hash = p.span();
+        tokens.next();
+        let attr = match tokens.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket => g,
+            _ => return Err(Error::new(hash, "expected an attribute")),
+        };
+
+        let mut inner = attr.stream().into_iter();
+        match inner.next() {
+            Some(TokenTree::Ident(i)) if i.to_string() == "parse" => {}
+            _ => continue,
+        }
+        let literal = match (inner.next(), inner.next()) {
+            (Some(TokenTree::Group(args)), None) if args.delimiter() == Delimiter::Parenthesis => {
+                let mut args = args.stream().into_iter();
+                match (args.next(), args.next()) {
+                    (Some(TokenTree::Literal(l)), None) => Some(l),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let literal = literal.ok_or_else(|| Error::new(attr.span(), "expected a format, like #[parse(\"{x},{y}\")]"))?;
+        if format.is_some() {
+            return Err(Error::new(attr.span(), "only one #[parse] format is allowed here"));
+        }
+        format = Some((unquote(&literal)?, literal.span()));
+    }
+    Ok(format)
+}
+
+// `pub`, `pub(crate)`, and so on
+fn skip_visibility(tokens: &mut Tokens) {
+    if let Some(TokenTree::Ident(i)) = tokens.peek() {
+        if i.to_string() == "pub" {
+            tokens.next();
+            if let Some(TokenTree::Group(g)) = tokens.peek() {
+                if g.delimiter() == Delimiter::Parenthesis {
+                    tokens.next();
+                }
+            }
+        }
+    }
+}
+
+fn expect_ident(tokens: &mut Tokens, after: Span) -> Result<Ident> {
+    match tokens.next() {
+        Some(TokenTree::Ident(i)) => Ok(i),
+        Some(other) => Err(Error::new(other.span(), "expected a name")),
+        None => Err(Error::new(after, "expected a name after this")),
+    }
+}
+
+// the body of a struct or variant: `{ a: A, b: B }`, `(A, B)`, or nothing at all
+fn read_fields(body: Option<TokenTree>) -> Result<Fields> {
+    let group = match body {
+        Some(TokenTree::Group(g)) if g.delimiter() != Delimiter::Bracket => g,
+        _ => return Ok(Fields::Unit),
+    };
+
+    let mut fields = Vec::new();
+    for (i, field) in split_commas(group.stream()).into_iter().enumerate() {
+        let mut tokens = field.into_iter().peekable();
+        read_attributes(&mut tokens)?;
+        skip_visibility(&mut tokens);
+        let (name, ident) = match group.delimiter() {
+            Delimiter::Brace => {
+                let ident = expect_ident(&mut tokens, group.span())?.to_string();
+                tokens.next(); // the `:`
+                (ident.trim_start_matches("r#").to_string(), ident)
+            }
+            _ => (i.to_string(), i.to_string()),
+        };
+        fields.push(Field { name, ident, ty: tokens.collect::<TokenStream>().to_string() });
+    }
+
+    Ok(match group.delimiter() {
+        Delimiter::Brace => Fields::Named(fields),
+        _ => Fields::Tuple(fields),
+    })
+}
+
+fn read_variants(body: TokenStream) -> Result<Vec<Variant>> {
+    split_commas(body).into_iter()
+        .map(|variant| {
+            let mut tokens = variant.into_iter().peekable();
+            let format = read_attributes(&mut tokens)?;
+            let name = expect_ident(&mut tokens, Span::call_site())?;
+            let fields = match tokens.peek() {
+                Some(TokenTree::Group(_)) => read_fields(tokens.next())?,
+                _ => Fields::Unit,
+            };
+            Ok(Variant { name: name.to_string(), span: name.span(), format, fields })
+        })
+        .collect()
+}
+
+// splits on the commas that aren't inside a type's <angle brackets>. anything inside () [] or {} is
+// already tucked away in a Group, so those take care of themselves
+fn split_commas(stream: TokenStream) -> Vec<TokenStream> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0;
+    let mut arrow = false;
+    for token in stream {
+        if let TokenTree::Punct(p) = &token {
+            match p.as_char() {
+                ',' if depth == 0 => {
+                    parts.push(Vec::new());
+                    continue;
+                }
+                '<' => depth += 1,
+                // the > in `fn() -> T` doesn't close anything
+                '>' if !arrow => depth -= 1,
+                _ => {}
+            }
+            arrow = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+        } else {
+            arrow = false;
+        }
+        parts.last_mut().unwrap().push(token);
+    }
+    parts.into_iter().filter(|part| !part.is_empty()).map(|part| part.into_iter().collect()).collect()
+}
+
+// the text of a string literal, as the compiler would see it, so escapes like \n and {{ work as expected
+fn unquote(literal: &Literal) -> Result<String> {
+    let text = literal.to_string();
+    let not_a_string = || Error::new(literal.span(), "expected a string literal");
+
+    if let Some(raw) = text.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return raw.get(hashes + 1..raw.len() - hashes - 1).map(str::to_string).ok_or_else(not_a_string);
+    }
+    let body = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).ok_or_else(not_a_string)?;
+
+    let mut result = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some('x') => {
+                let hex = chars.by_ref().take(2).collect::<String>();
+                result.push(u8::from_str_radix(&hex, 16).map_err(|_| not_a_string())? as char);
+            }
+            Some('u') => {
+                let hex = chars.by_ref().skip(1).take_while(|c| *c != '}').collect::<String>();
+                let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                result.push(code.ok_or_else(not_a_string)?);
+            }
+            // a backslash at the end of a line skips the newline and any indentation after it
+            Some('\n') => {
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            Some(c) => result.push(c),
+            None => return Err(not_a_string()),
+        }
+    }
+    Ok(result)
+}
+
+enum Piece {
+    Text(String),
+    Field(String, Span),
+}
+
+// splits a format like "{n1}-{n2} {letter}" into the fields and the text in between. {{ and }} are literal braces
+fn read_format(format: &str, span: Span) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(Error::new(span, "unclosed `{` in format, use `{{` for a literal `{`")),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Field(name.trim().to_string(), span));
+            }
+            '}' => return Err(Error::new(span, "unmatched `}` in format, use `}}` for a literal `}`")),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn generate(item: &Item) -> Result<String> {
+    let (parser, grammar) = match &item.kind {
+        Kind::Struct(variant) => generate_variant(variant, "Self")?,
+        Kind::Enum(variants) => {
+            let mut variants = variants.iter()
+                .map(|variant| generate_variant(variant, &format!("Self::{}", variant.name)));
+            let first = variants.next().unwrap_or_else(|| Err(Error::new(Span::call_site(), "an enum with no variants can't be parsed")))?;
+            // each variant in the order they're written, the first one that matches wins
+            variants.try_fold(first, |(parser, grammar), variant| {
+                let (next_parser, next_grammar) = variant?;
+                Ok((format!("::lib::parse::Parser::or({}, {})", parser, next_parser), format!("{}.or({})", grammar, next_grammar)))
+            })?
+        }
+    };
+
+    Ok(format!(r#"
+        impl ::lib::parse::Parse for {name} {{
+            fn parser<'a>() -> ::lib::parse::BoxedParser<'a, Self> {{
+                let parser = {parser};
+                ::lib::parse::Parser::boxed(::lib::parse::Parser::describe(parser, || {{
+                    ::lib::parse::Grammar::rule({name:?}, {grammar})
+                }}))
+            }}
+        }}
+
+        impl ::std::str::FromStr for {name} {{
+            type Err = ::lib::parse::ParseError;
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {{
+                ::lib::parse::Parser::parse_complete(&<Self as ::lib::parse::Parse>::parser(), s)
+            }}
+        }}
+    "#, name = item.name, parser = parser, grammar = grammar))
+}
+
+// an expression for a parser that reads `variant` according to its format, and builds it with `constructor`,
+// along with one for the grammar that describes it
+fn generate_variant(variant: &Variant, constructor: &str) -> Result<(String, String)> {
+    let (format, span) = variant.format.as_ref()
+        .ok_or_else(|| Error::new(variant.span, format!("`{}` needs a format, like #[parse(\"{{x}},{{y}}\")]", variant.name)))?;
+    let pieces = read_format(format, *span)?;
+    let fields = variant.fields.all();
+
+    // every field has to show up in the format exactly once, otherwise we either can't build it, or don't
+    // know which of the two values to keep
+    for field in fields {
+        let uses = pieces.iter().filter(|p| matches!(p, Piece::Field(name, _) if *name == field.name)).count();
+        if uses != 1 {
+            let problem = if uses == 0 { "is missing from" } else { "appears more than once in" };
+            return Err(Error::new(*span, format!("`{}` {} the format for `{}`", field.name, problem, variant.name)));
+        }
+    }
+
+    let mut setup = String::new();
+    let mut steps = String::new();
+    let mut grammar = String::from("::lib::parse::Grammar::empty()");
+    for piece in &pieces {
+        match piece {
+            Piece::Text(text) => {
+                steps += &format!("let (_, input) = ::lib::parse::Parser::parse_furthest(&{:?}, input, passed)?;\n", text);
+                grammar += &format!(".then(::lib::parse::Grammar::literal({:?}))", text);
+            }
+            Piece::Field(name, span) => {
+                let field = fields.iter().find(|f| f.name == *name)
+                    .ok_or_else(|| Error::new(*span, format!("`{}` has no field called `{}`", variant.name, name)))?;
+                setup += &format!("let parser_{} = <{} as ::lib::parse::Parse>::parser();\n", name, field.ty);
+                steps += &format!("let (field_{0}, input) = ::lib::parse::Parser::parse_furthest(&parser_{0}, input, passed)?;\n", name);
+                grammar += &format!(".then(::lib::parse::Parser::grammar(&<{} as ::lib::parse::Parse>::parser()))", field.ty);
+            }
+        }
+    }
+
+    let build = match &variant.fields {
+        Fields::Named(fields) => format!("{} {{ {} }}", constructor, fields.iter()
+            .map(|f| format!("{}: field_{}", f.ident, f.name)).collect::<Vec<_>>().join(", ")),
+        Fields::Tuple(fields) => format!("{}({})", constructor, fields.iter()
+            .map(|f| format!("field_{}", f.name)).collect::<Vec<_>>().join(", ")),
+        Fields::Unit => constructor.to_string(),
+    };
+
+    // the steps share what they pass over, so a failure partway through gets merged with whatever stopped
+    // the fields before it, the same as a chain of `then`s would
+    let parser = format!(r#"{{
+        {setup}
+        ::lib::parse::sequence(move |input: &'a str, passed: &mut ::std::option::Option<::lib::parse::Error>| -> ::lib::parse::Result<'a, Self> {{
+            {steps}
+            ::std::result::Result::Ok(({build}, input))
+        }})
+    }}"#, setup = setup, steps = steps, build = build);
+    Ok((parser, grammar))
+}
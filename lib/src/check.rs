@@ -0,0 +1,461 @@
+/*
+    property testing, without pulling in quickcheck or proptest. a property is a function that should be true
+    for every value a generator can make, like "uint32() reads every string of digits the same way
+    str::parse does". checking it throws a few hundred made-up values at it, and if any of them make it false,
+    shrinks that value down to the smallest one that still does, since the first failure is usually a long
+    mess, and the smallest one usually points right at the problem.
+
+    everything comes from lib::rng, starting from one seed, so the same seed always makes the same values and
+    the same failure. the seed is fixed unless LIB_CHECK_SEED says otherwise, which keeps `cargo test` the same
+    from run to run, and a failure says which seed it came from so it can be run again.
+*/
+use std::fmt::{self, Debug};
+use std::ops::{Bound, RangeBounds};
+
+use crate::parse::{Grammar, Repetition};
+use crate::rng::Rng;
+
+/// Something that makes up values to check a property with, and knows how to make them smaller
+pub trait Gen {
+    type Value: Clone + Debug;
+
+    fn generate(&self, rng: &mut Rng) -> Self::Value;
+
+    /// values a bit simpler than `value`, most promising first. nothing, if it's as simple as it gets
+    fn shrink(&self, _value: &Self::Value) -> Vec<Self::Value> {
+        Vec::new()
+    }
+}
+
+/// Checks properties, made by `Check::new()`, or just use `check` for the defaults
+/// ```
+/// use lib::check::*;
+///
+/// // every u8 fits in a u16, which always works
+/// Check::new().cases(100).run(ints::<u8>(..), |&n| u16::from(n) as u8 == n);
+///
+/// // but not every u16 fits in a u8, and the smallest one that doesn't is 256
+/// let failure = Check::new().try_run(ints::<u16>(..), |&n| n < 256).unwrap_err();
+/// assert_eq!(failure.minimal, 256);
+/// assert!(failure.to_string().contains("minimal counterexample: 256"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Check {
+    cases: usize,
+    seed: u64,
+}
+
+/// A value that a property was false for, and how to get it back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure<T> {
+    pub seed: u64,
+    /// which case it was, counting from 1
+    pub case: usize,
+    /// the value as it was made up, before shrinking
+    pub original: T,
+    /// the smallest value we could find that the property is still false for
+    pub minimal: T,
+    pub shrinks: usize,
+}
+
+/// the seed when there's no LIB_CHECK_SEED
+const DEFAULT_SEED: u64 = 2020;
+/// how many times we'll shrink a failure before settling for what we've got
+const MAX_SHRINKS: usize = 1000;
+
+impl Check {
+    /// 256 cases, with the seed from LIB_CHECK_SEED if it's set
+    pub fn new() -> Self {
+        let seed = std::env::var("LIB_CHECK_SEED").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_SEED);
+        Self { cases: 256, seed }
+    }
+
+    /// how many values to try
+    pub fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// checks that `property` is true for every value `gen` makes, and panics with the seed and the smallest
+    /// counterexample if it isn't
+    pub fn run<G: Gen>(&self, gen: G, property: impl Fn(&G::Value) -> bool) {
+        if let Err(failure) = self.try_run(gen, property) {
+            panic!("{}", failure);
+        }
+    }
+
+    /// checks that `property` is true for every value `gen` makes, or says what it's false for
+    pub fn try_run<G: Gen>(&self, gen: G, property: impl Fn(&G::Value) -> bool) -> Result<(), Failure<G::Value>> {
+        let mut rng = Rng::new(self.seed);
+        for case in 1..=self.cases {
+            let original = gen.generate(&mut rng);
+            if property(&original) {
+                continue;
+            }
+
+            // keep taking the first simpler value that still fails, until there aren't any
+            let mut minimal = original.clone();
+            let mut shrinks = 0;
+            while shrinks < MAX_SHRINKS {
+                match gen.shrink(&minimal).into_iter().find(|smaller| !property(smaller)) {
+                    Some(smaller) => {
+                        minimal = smaller;
+                        shrinks += 1;
+                    }
+                    None => break,
+                }
+            }
+            return Err(Failure { seed: self.seed, case, original, minimal, shrinks });
+        }
+        Ok(())
+    }
+}
+
+impl Default for Check {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks that `property` is true for every value `gen` makes, with the default number of cases and seed
+/// ```
+/// use lib::check::*;
+/// use lib::parse::*;
+///
+/// check(strings("0123456789", 1..=12), |digits| {
+///     uint32().parse_complete(digits).ok() == digits.parse::<u32>().ok()
+/// });
+/// ```
+pub fn check<G: Gen>(gen: G, property: impl Fn(&G::Value) -> bool) {
+    Check::new().run(gen, property)
+}
+
+impl<T: Debug> fmt::Display for Failure<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "property failed on case {} with seed {} (set LIB_CHECK_SEED={} to run it again)", self.case, self.seed, self.seed)?;
+        writeln!(f, "minimal counterexample: {:?}", self.minimal)?;
+        write!(f, "shrunk {} times, from: {:?}", self.shrinks, self.original)
+    }
+}
+
+/// An integer type that `ints` can make
+pub trait Int: Copy + Debug {
+    const MIN: i128;
+    const MAX: i128;
+
+    fn to_i128(self) -> i128;
+    fn from_i128(n: i128) -> Self;
+}
+
+macro_rules! impl_int {
+    ($($t:ty),*) => {
+        $(
+            impl Int for $t {
+                const MIN: i128 = <$t>::MIN as i128;
+                const MAX: i128 = <$t>::MAX as i128;
+
+                fn to_i128(self) -> i128 {
+                    self as i128
+                }
+
+                fn from_i128(n: i128) -> Self {
+                    n as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// The generator made by `ints`
+#[derive(Debug, Clone)]
+pub struct Ints<T> {
+    lo: i128,
+    hi: i128,
+    _t: std::marker::PhantomData<fn() -> T>,
+}
+
+/// Integers in `range`, which shrink towards zero, or whichever end of the range is closest to it. The ends of
+/// the range and zero come up more often than they would by chance, since that's where the bugs usually are
+pub fn ints<T: Int>(range: impl RangeBounds<T>) -> Ints<T> {
+    let lo = match range.start_bound() {
+        Bound::Included(n) => n.to_i128(),
+        Bound::Excluded(n) => n.to_i128() + 1,
+        Bound::Unbounded => T::MIN,
+    };
+    let hi = match range.end_bound() {
+        Bound::Included(n) => n.to_i128(),
+        Bound::Excluded(n) => n.to_i128() - 1,
+        Bound::Unbounded => T::MAX,
+    };
+    assert!(lo <= hi, "there are no integers from {} to {}", lo, hi);
+    Ints { lo, hi, _t: std::marker::PhantomData }
+}
+
+impl<T> Ints<T> {
+    fn simplest(&self) -> i128 {
+        0.clamp(self.lo, self.hi)
+    }
+}
+
+impl<T: Int> Gen for Ints<T> {
+    type Value = T;
+
+    fn generate(&self, rng: &mut Rng) -> T {
+        if rng.chance(0.125) {
+            return T::from_i128(*rng.choose(&[self.lo, self.hi, self.simplest()]).unwrap());
+        }
+        // every range of integers we can make fits in a u64 once it's moved to start at zero
+        T::from_i128(self.lo + rng.between(0, (self.hi - self.lo) as u64) as i128)
+    }
+
+    // the simplest value, then halfway there, then a quarter of the way, and so on, and then one step closer
+    fn shrink(&self, value: &T) -> Vec<T> {
+        let value = value.to_i128();
+        let target = self.simplest();
+        let mut smaller = Vec::new();
+        let mut distance = value - target;
+        while distance != 0 {
+            smaller.push(T::from_i128(value - distance));
+            distance /= 2;
+        }
+        if value != target {
+            smaller.push(T::from_i128(value - (value - target).signum()));
+        }
+        smaller.dedup_by_key(|n| n.to_i128());
+        smaller
+    }
+}
+
+/// how much longer than its minimum a vector or string gets, when there's no maximum
+const MAX_EXTRA_LEN: usize = 16;
+
+fn length(len: Repetition, rng: &mut Rng) -> usize {
+    let (min, max) = len.range();
+    rng.between(min as u64, max.unwrap_or(min + MAX_EXTRA_LEN) as u64) as usize
+}
+
+// shorter vectors first, with the biggest chunks taken out first, then the same length with one item shrunk
+fn shrink_vec<T: Clone>(items: &[T], min: usize, shrink_item: impl Fn(&T) -> Vec<T>) -> Vec<Vec<T>> {
+    let mut smaller = Vec::new();
+    let mut chunk = items.len().saturating_sub(min);
+    while chunk > 0 {
+        for start in (0..=items.len() - chunk).step_by(chunk) {
+            smaller.push([&items[..start], &items[start + chunk..]].concat());
+        }
+        chunk /= 2;
+    }
+    for (i, item) in items.iter().enumerate() {
+        for simpler in shrink_item(item) {
+            let mut items = items.to_vec();
+            items[i] = simpler;
+            smaller.push(items);
+        }
+    }
+    smaller
+}
+
+/// The generator made by `vecs`
+#[derive(Debug, Clone)]
+pub struct Vecs<G> {
+    item: G,
+    len: Repetition,
+}
+
+/// Vectors of `item`s, as many as `len` allows, which shrink by dropping items and shrinking the ones left
+pub fn vecs<G: Gen>(item: G, len: impl Into<Repetition>) -> Vecs<G> {
+    Vecs { item, len: len.into() }
+}
+
+impl<G: Gen> Gen for Vecs<G> {
+    type Value = Vec<G::Value>;
+
+    fn generate(&self, rng: &mut Rng) -> Self::Value {
+        (0..length(self.len, rng)).map(|_| self.item.generate(rng)).collect()
+    }
+
+    fn shrink(&self, value: &Self::Value) -> Vec<Self::Value> {
+        shrink_vec(value, self.len.min(), |item| self.item.shrink(item))
+    }
+}
+
+/// The generator made by `strings`
+#[derive(Debug, Clone)]
+pub struct Strings {
+    alphabet: Vec<char>,
+    len: Repetition,
+}
+
+/// Strings made of the characters in `alphabet`, as many as `len` allows. They shrink by dropping characters,
+/// and by swapping them for ones earlier in the alphabet
+pub fn strings(alphabet: &str, len: impl Into<Repetition>) -> Strings {
+    let alphabet = alphabet.chars().collect::<Vec<_>>();
+    assert!(!alphabet.is_empty(), "strings need at least one character to be made of");
+    Strings { alphabet, len: len.into() }
+}
+
+impl Gen for Strings {
+    type Value = String;
+
+    fn generate(&self, rng: &mut Rng) -> String {
+        (0..length(self.len, rng)).map(|_| *rng.choose(&self.alphabet).unwrap()).collect()
+    }
+
+    fn shrink(&self, value: &String) -> Vec<String> {
+        let chars = value.chars().collect::<Vec<_>>();
+        let earlier = |c: &char| {
+            let i = self.alphabet.iter().position(|a| a == c).unwrap_or(0);
+            self.alphabet[..i].first().copied().into_iter().collect()
+        };
+        shrink_vec(&chars, self.len.min(), earlier).into_iter().map(|chars| chars.into_iter().collect()).collect()
+    }
+}
+
+/// The generator made by `grids`
+#[derive(Debug, Clone)]
+pub struct Grids<G> {
+    cell: G,
+    width: Repetition,
+    height: Repetition,
+}
+
+/// Rectangular grids of `cell`s, as rows, with as many columns and rows as `width` and `height` allow. They
+/// shrink by dropping a row or a column, and by shrinking cells
+/// ```
+/// use lib::check::*;
+///
+/// // there's always a corner to start from
+/// check(grids(strings(".#", 1), 1..=8, 1..=8), |rows| rows[0].len() == rows.last().unwrap().len());
+/// ```
+pub fn grids<G: Gen>(cell: G, width: impl Into<Repetition>, height: impl Into<Repetition>) -> Grids<G> {
+    Grids { cell, width: width.into(), height: height.into() }
+}
+
+impl<G: Gen> Gen for Grids<G> {
+    type Value = Vec<Vec<G::Value>>;
+
+    fn generate(&self, rng: &mut Rng) -> Self::Value {
+        let width = length(self.width, rng);
+        (0..length(self.height, rng)).map(|_| (0..width).map(|_| self.cell.generate(rng)).collect()).collect()
+    }
+
+    fn shrink(&self, rows: &Self::Value) -> Vec<Self::Value> {
+        let mut smaller = Vec::new();
+        if rows.len() > self.height.min() {
+            for y in 0..rows.len() {
+                smaller.push([&rows[..y], &rows[y + 1..]].concat());
+            }
+        }
+        let width = rows.first().map_or(0, Vec::len);
+        if width > self.width.min() {
+            for x in 0..width {
+                smaller.push(rows.iter().map(|row| [&row[..x], &row[x + 1..]].concat()).collect());
+            }
+        }
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                for simpler in self.cell.shrink(cell) {
+                    let mut rows = rows.clone();
+                    rows[y][x] = simpler;
+                    smaller.push(rows);
+                }
+            }
+        }
+        smaller
+    }
+}
+
+/// The generator made by `samples`
+#[derive(Debug, Clone)]
+pub struct Samples {
+    grammar: Grammar,
+}
+
+/// Text that `grammar` matches, like what a parser's `.grammar()` describes. These don't shrink, since all
+/// we'd know about a smaller string is whether it still matches
+pub fn samples(grammar: Grammar) -> Samples {
+    Samples { grammar }
+}
+
+impl Gen for Samples {
+    type Value = String;
+
+    fn generate(&self, rng: &mut Rng) -> String {
+        self.grammar.sample(rng).expect("only a grammar with nothing opaque in it can be sampled")
+    }
+}
+
+macro_rules! impl_gen_tuple {
+    ($(($($g:ident $i:tt),*)),*) => {
+        $(
+            /// a value from each, which shrink one at a time
+            impl<$($g: Gen),*> Gen for ($($g,)*) {
+                type Value = ($($g::Value,)*);
+
+                fn generate(&self, rng: &mut Rng) -> Self::Value {
+                    ($(self.$i.generate(rng),)*)
+                }
+
+                fn shrink(&self, value: &Self::Value) -> Vec<Self::Value> {
+                    let mut smaller = Vec::new();
+                    $(
+                        for simpler in self.$i.shrink(&value.$i) {
+                            let mut value = value.clone();
+                            value.$i = simpler;
+                            smaller.push(value);
+                        }
+                    )*
+                    smaller
+                }
+            }
+        )*
+    };
+}
+
+impl_gen_tuple!((A 0, B 1), (A 0, B 1, C 2), (A 0, B 1, C 2, D 3));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::*;
+
+    #[test]
+    fn test_check_shrinks_vectors_to_the_smallest_failure() {
+        // no vector of small numbers sums to more than 100, which isn't true
+        let failure = Check::new().try_run(vecs(ints(0..=50u32), Any), |ns| ns.iter().sum::<u32>() <= 100).unwrap_err();
+        assert_eq!(failure.minimal.iter().sum::<u32>(), 101);
+
+        let again = Check::new().seed(failure.seed).try_run(vecs(ints(0..=50u32), Any), |ns| ns.iter().sum::<u32>() <= 100);
+        assert_eq!(again, Err(failure));
+    }
+
+    #[test]
+    fn test_check_ints_shrink_towards_zero_within_range() {
+        assert_eq!(ints(-10..=-3i32).shrink(&-8), vec![-3, -6, -7]);
+        assert_eq!(ints(..=100u8).shrink(&0), vec![]);
+        let failure = Check::new().try_run(ints::<i64>(..), |&n| n > -1000).unwrap_err();
+        assert_eq!(failure.minimal, -1000);
+    }
+
+    #[test]
+    fn test_check_parses_what_grammar_samples() {
+        fn pair<'a>() -> impl Parser<'a, (u32, u32)> {
+            uint32().skip(",").then(uint32())
+        }
+        check(samples(pair().grammar()), |text| pair().parse_complete(text).is_ok());
+    }
+
+    #[test]
+    fn test_check_grid_shrinks_to_a_single_cell() {
+        let failure = Check::new().try_run(grids(strings(".#", 1), 1..=6, 1..=6), |rows| {
+            rows.iter().flatten().all(|cell| cell == ".")
+        }).unwrap_err();
+        assert_eq!(failure.minimal, vec![vec!["#".to_string()]]);
+    }
+}
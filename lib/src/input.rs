@@ -0,0 +1,127 @@
+/*
+    tidying up a puzzle input before anything parses it, and splitting it into the pieces most puzzles come in:
+    lines, groups of lines with blank lines between them, or a number on every line.
+
+    inputs get saved out of a browser, pasted into an editor, or checked out on windows, so they can turn up with
+    "\r\n" line endings, spaces on the ends of lines, or an extra blank line at the end. none of that ever means
+    anything in a puzzle, but any of it can make a parser that's expecting " " or "\n" come out with the wrong
+    answer instead of an error. so `tidy` takes all of it out once, up front. trimming the ends of lines never
+    joins or splits any, so line numbers still match the original file.
+*/
+use std::borrow::Cow;
+
+use crate::parse::*;
+
+/// A puzzle input, with `"\r\n"` turned into `"\n"`, and whitespace trimmed off the end of every line and off
+/// the end of the whole thing
+pub struct Text<'a> {
+    text: Cow<'a, str>,
+}
+
+/// Tidies up `input`, which only gets copied if there was something to tidy
+/// ```
+/// use lib::input::*;
+///
+/// let text = tidy("1 2 \r\n3\r\n\r\n4\n\n");
+/// assert_eq!(text.as_str(), "1 2\n3\n\n4");
+/// assert_eq!(text.lines().collect::<Vec<_>>(), vec![(1, "1 2"), (2, "3"), (3, ""), (4, "4")]);
+/// assert_eq!(text.groups().collect::<Vec<_>>(), vec![(1, "1 2\n3"), (4, "4")]);
+/// ```
+pub fn tidy(input: &str) -> Text<'_> {
+    let input = input.trim_end();
+    let text = if input.split('\n').all(|line| line.len() == line.trim_end().len()) {
+        Cow::Borrowed(input)
+    } else {
+        Cow::Owned(input.lines().map(str::trim_end).collect::<Vec<_>>().join("\n"))
+    };
+    Text { text }
+}
+
+impl<'a> Text<'a> {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// every line, with its 1-based line number
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.text.lines().enumerate().map(|(i, line)| (i + 1, line))
+    }
+
+    /// every run of lines that aren't blank, joined back up with `"\n"`, along with the line number it starts on.
+    /// any number of blank lines can separate them
+    /// ```
+    /// use lib::input::*;
+    /// let text = tidy("\na\n\n\n\nb\nc");
+    /// assert_eq!(text.groups().collect::<Vec<_>>(), vec![(2, "a"), (6, "b\nc")]);
+    /// ```
+    pub fn groups(&self) -> impl Iterator<Item = (usize, &str)> {
+        let text = self.as_str();
+        let mut lines = self.lines().peekable();
+        std::iter::from_fn(move || {
+            let (line, first) = lines.find(|(_, line)| !line.is_empty())?;
+            let mut last = first;
+            while let Some((_, next)) = lines.next_if(|(_, line)| !line.is_empty()) {
+                last = next;
+            }
+            Some((line, &text[offset(text, first)..offset(text, last) + last.len()]))
+        })
+    }
+
+    /// parses every line as a number, and gives each one back with its line number. errors are located in the
+    /// whole input, so they point at the right line
+    /// ```
+    /// use lib::input::*;
+    ///
+    /// let text = tidy("1721\n979 \r\n-366\n");
+    /// let numbers = text.numbers::<i32>().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(numbers, vec![(1, 1721), (2, 979), (3, -366)]);
+    ///
+    /// let err = tidy("1\n2\n3x").numbers::<u8>().nth(2).unwrap().unwrap_err();
+    /// assert_eq!((err.line, err.column), (3, 2));
+    /// assert_eq!(err.to_string().lines().next(), Some("error: expected end of line, found 'x'"));
+    /// ```
+    pub fn numbers<'t, T: Integer + 't>(&'t self) -> impl Iterator<Item = std::result::Result<(usize, T), ParseError>> + 't {
+        let text = self.as_str();
+        let number = integer::<T>();
+        self.lines().map(move |(n, line)| {
+            let result = match number.parse(line) {
+                Ok((value, "")) => Ok(value),
+                Ok((_, rest)) => Err(Error::new(rest, Expected::named("end of line"))),
+                Err(e) => Err(e),
+            };
+            // errors only know how much of the line was left, so add back everything after it
+            let after = text.len() - offset(text, line) - line.len();
+            result.map(|value| (n, value)).map_err(|e| {
+                let remaining = e.remaining() + after;
+                e.moved_to(remaining).locate(text)
+            })
+        })
+    }
+}
+
+// where `part`, which has to be a slice of `text`, starts in it
+fn offset(text: &str, part: &str) -> usize {
+    part.as_ptr() as usize - text.as_ptr() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tidy_only_copies_when_it_has_to() {
+        assert!(matches!(tidy("a\nb\n\n").text, Cow::Borrowed("a\nb")));
+        assert!(matches!(tidy("a\r\nb").text, Cow::Owned(_)));
+        assert!(matches!(tidy("a\t\nb").text, Cow::Owned(_)));
+        assert_eq!(tidy(" \n \r\n").lines().count(), 0);
+        assert_eq!(tidy("").groups().count(), 0);
+    }
+
+    #[test]
+    fn test_groups_with_crlf_and_trailing_blank_lines() {
+        let unix = "a:1 b:2\nc:3\n\nd:4\n";
+        let windows = "a:1 b:2 \r\nc:3\r\n\r\nd:4\r\n\r\n  \r\n";
+        assert_eq!(tidy(unix).groups().collect::<Vec<_>>(), tidy(windows).groups().collect::<Vec<_>>());
+        assert_eq!(tidy(windows).groups().last(), Some((4, "d:4")));
+    }
+}
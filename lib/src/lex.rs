@@ -0,0 +1,235 @@
+/*
+    a lexer, for grammars that are nicer to parse as a list of tokens than as raw text.
+
+    the idea is to do all the fiddly character-level stuff (whitespace, comments, what counts as a number)
+    up front, using the same parsers we'd use anyway, and then write the actual grammar over token kinds.
+    since &[Token] is an Input, all the usual combinators work on the result.
+*/
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+use crate::parse::*;
+
+/// One token out of a `Lexer`: what kind it is, the text it matched, and where it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a, K> {
+    pub kind: K,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+/// what the parsers over a lexer's output take as their input
+pub type Tokens<'a, K> = &'a [Token<'a, K>];
+
+impl<'a, K: Copy + Debug> SliceItem for Token<'a, K> {
+    fn to_snippet(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.text)
+    }
+
+    fn describe(&self) -> String {
+        format!("{:?} {:?}", self.kind, self.text)
+    }
+
+    fn is_newline(&self) -> bool {
+        self.text.ends_with('\n')
+    }
+}
+
+/// Splits text up into tokens, according to a list of rules.
+///
+/// Each rule pairs a token kind with a parser that matches it. At each point in the input, anything matching
+/// an `ignore` rule is skipped over, and then whichever token rule matches the most input wins, with ties
+/// going to whichever rule was added first. So keywords should be added before identifiers, but `<=` and
+/// `<` can go in any order.
+/// ```
+/// use lib::parse::*;
+/// use lib::lex::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind { Op, Num, Sign }
+///
+/// let lexer = Lexer::new()
+///     .token(Kind::Op, ["nop", "acc", "jmp"].as_ref())
+///     .token(Kind::Sign, "+".or("-"))
+///     .token(Kind::Num, uint32())
+///     .ignore_whitespace()
+///     .ignore_line_comments("#");
+///
+/// let source = "nop +0  # does nothing\nacc +1\njmp -4\n";
+/// let tokens = lexer.lex(source).unwrap();
+/// assert_eq!(tokens.len(), 9);
+/// assert_eq!((tokens[4].kind, tokens[4].text), (Kind::Sign, "+"));
+/// assert_eq!(tokens[4].span.start_line_col(source), LineCol { line: 2, column: 5 });
+///
+/// let offset = token(Kind::Sign).then(token(Kind::Num))
+///     .map(|(sign, n)| n.text.parse::<i32>().unwrap() * if sign.text == "-" { -1 } else { 1 });
+/// let instruction = token(Kind::Op).then(offset).map(|(op, n)| (op.text, n));
+/// assert_eq!(
+///     instruction.repeat(Any).parse_result(&tokens[..]),
+///     Ok(vec![("nop", 0), ("acc", 1), ("jmp", -4)]),
+/// );
+/// ```
+pub struct Lexer<'a, K> {
+    rules: Vec<(K, BoxedParser<'a, ()>)>,
+    ignored: Vec<BoxedParser<'a, ()>>,
+}
+
+impl<'a, K: Copy + Debug> Lexer<'a, K> {
+    pub fn new() -> Self {
+        Self { rules: Vec::new(), ignored: Vec::new() }
+    }
+
+    /// adds a rule, producing a `kind` token for whatever `matcher` matches
+    pub fn token<T: 'a>(mut self, kind: K, matcher: impl Parser<'a, T> + 'a) -> Self {
+        self.rules.push((kind, matcher.map(|_| ()).boxed()));
+        self
+    }
+
+    /// skips over anything `matcher` matches, between tokens
+    pub fn ignore<T: 'a>(mut self, matcher: impl Parser<'a, T> + 'a) -> Self {
+        self.ignored.push(matcher.map(|_| ()).boxed());
+        self
+    }
+
+    /// skips all whitespace, including newlines, between tokens
+    pub fn ignore_whitespace(self) -> Self {
+        self.ignore(whitespace().repeat(Many))
+    }
+
+    /// skips comments that start with `prefix` and run to the end of the line
+    pub fn ignore_line_comments(self, prefix: &'static str) -> Self {
+        self.ignore(prefix.then(take_while(|c| c != '\n')))
+    }
+
+    /// splits all of `input` into tokens, or reports the first spot where no rule matched
+    pub fn lex(&self, input: &'a str) -> std::result::Result<Vec<Token<'a, K>>, ParseError> {
+        self.parse_result(input)
+    }
+
+    fn skip_ignored(&self, mut input: &'a str) -> &'a str {
+        while let Some(rest) = self.ignored.iter()
+            .filter_map(|p| p.parse(input).ok())
+            .map(|(_, rest)| rest)
+            .find(|rest| rest.len() < input.len())
+        {
+            input = rest;
+        }
+        input
+    }
+}
+
+impl<'a, K: Copy + Debug> Default for Lexer<'a, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// a lexer is a parser that produces every token in the input, stopping only at the end of it
+impl<'a, K: Copy + Debug> Parser<'a, Vec<Token<'a, K>>> for Lexer<'a, K> {
+    fn parse(&self, input: &'a str) -> Result<'a, Vec<Token<'a, K>>> {
+        let mut input = input;
+        let mut tokens = Vec::new();
+        loop {
+            input = self.skip_ignored(input);
+            if input.is_empty() {
+                return Ok((tokens, input));
+            }
+
+            // longest match wins; a rule that matches nothing can't make progress, so doesn't count
+            let mut best: Option<(K, &'a str)> = None;
+            for (kind, matcher) in &self.rules {
+                if let Ok(((), rest)) = matcher.parse(input) {
+                    if rest.len() < best.map_or(input.len(), |(_, r)| r.len()) {
+                        best = Some((*kind, rest));
+                    }
+                }
+            }
+
+            let (kind, rest) = match best {
+                Some(best) => best,
+                // only bother collecting everyone's errors once we know we're going to need them
+                None => return Err(self.rules.iter()
+                    .filter_map(|(_, matcher)| matcher.parse(input).err())
+                    .fold(Error::unexpected(input), Error::merge)),
+            };
+            let span = Span::between(input, rest);
+            tokens.push(Token { kind, text: &input[..span.len()], span });
+            input = rest;
+        }
+    }
+}
+
+/// Creates a Parser that matches one token of the given kind
+pub fn token<'a, K: Copy + Debug + PartialEq>(kind: K) -> impl Parser<'a, Token<'a, K>, Tokens<'a, K>> {
+    move |input: Tokens<'a, K>| match input.split_first() {
+        Some((token, rest)) if token.kind == kind => Ok((*token, rest)),
+        _ => Err(Error::new(input, Expected::named(format!("{:?}", kind)))),
+    }
+}
+
+/// Creates a Parser that matches one token of the given kind, with exactly the given text
+pub fn token_text<'a, K: Copy + Debug + PartialEq>(kind: K, text: &'static str) -> impl Parser<'a, Token<'a, K>, Tokens<'a, K>> {
+    move |input: Tokens<'a, K>| match input.split_first() {
+        Some((token, rest)) if token.kind == kind && token.text == text => Ok((*token, rest)),
+        _ => Err(Error::new(input, Expected::static_literal(text))),
+    }
+}
+
+/// Runs `parser` over `tokens`, which were lexed from `source`, and reports any failure as a spot in `source`
+/// rather than as an index into the list of tokens
+/// ```
+/// use lib::parse::*;
+/// use lib::lex::*;
+///
+/// let lexer = Lexer::new().token("num", uint32()).token("op", "+").ignore_whitespace();
+/// let source = "1 + 2\n+ + 3";
+/// let tokens = lexer.lex(source).unwrap();
+///
+/// let sum = token("num").repeat_delimited(Many, token("op"));
+/// let err = parse_tokens(source, &tokens, sum.then(token("op")).then(token("num"))).unwrap_err();
+/// assert_eq!((err.line, err.column), (2, 3));
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected \"num\", found '+'"));
+/// ```
+pub fn parse_tokens<'a, T, K: Copy + Debug>(
+    source: &'a str,
+    tokens: Tokens<'a, K>,
+    parser: impl Parser<'a, T, Tokens<'a, K>>,
+) -> std::result::Result<T, ParseError> {
+    match parser.parse(tokens) {
+        Ok((val, _rest)) => Ok(val),
+        Err(err) => {
+            let index = tokens.len() - err.remaining();
+            let remaining_source = tokens.get(index).map_or(0, |t| source.len() - t.span.start(source));
+            Err(err.moved_to(remaining_source).locate(source))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lex_reports_unmatched_input() {
+        let lexer = Lexer::new()
+            .token("num", uint32())
+            .token("op", "+".or("-"))
+            .ignore(" ");
+        let err = lexer.lex("1 + 2 * 3").unwrap_err();
+        assert_eq!((err.offset, err.found.as_deref()), (6, Some("'*'")));
+        assert_eq!(err.expected, vec![Expected::literal("+"), Expected::literal("-"), Expected::named("unsigned integer")]);
+    }
+
+    #[test]
+    fn test_lex_longest_match_then_first_rule() {
+        let lexer = Lexer::new()
+            .token("keyword", "if")
+            .token("ident", identifier_str())
+            .token("cmp", "<")
+            .token("cmp", "<=")
+            .ignore_whitespace();
+        let tokens = lexer.lex("if iffy <= x").unwrap();
+        let kinds = tokens.iter().map(|t| (t.kind, t.text)).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![("keyword", "if"), ("ident", "iffy"), ("cmp", "<="), ("ident", "x")]);
+    }
+}
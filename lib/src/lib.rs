@@ -1,2 +1,11 @@
+// so code generated by #[derive(Parse)], which says ::lib::parse, works in here too
+extern crate self as lib;
+
 pub mod parse;
-pub mod grid;
\ No newline at end of file
+pub mod grid;
+pub mod input;
+pub mod lex;
+pub mod records;
+pub mod rng;
+pub mod check;
+pub mod validate;
\ No newline at end of file
@@ -1,4 +1,4 @@
-/**
+/*
     largely inspired by https://bodil.lol/parser-combinators/# and nom
     but nom is cheating :P
 
@@ -14,245 +14,496 @@
 */
 
 /*
-    bodil's article uses Result<(rest, result), input>, but I think that's a bit overkill
-    for this quick and dirty lib, then you have to juggle error types everywhere, it's messy.
+    bodil's article uses Result<(rest, result), input>, and originally I thought that was a bit overkill
+    for this quick and dirty lib, so parse::Result<T> was just Option<(result, rest)>.
 
-    plus, the order of the tuple seems weird to me... if you match "a" in "abc", my mental model
-    is that we split to "a" and "bc", which is (result, rest) instead.
+    turns out "None" is a pretty miserable thing to get back from a 1000-line input, so now it's
+    Result<(result, rest), Error>. the order of the tuple stays (result, rest) though... if you match
+    "a" in "abc", my mental model is still that we split to "a" and "bc".
 
-    so, we'll simplify, and say parse::Result<T> is Option<(result, rest)>. None means we couldn't parse.
+    Error remembers where things went wrong and what we were hoping to see there. whenever a combinator
+    has two failures on hand (like both sides of an `or`), it merges them, and the one that got further wins.
+    that includes the failures we get past, like whatever stopped a `repeat`: "abce" stopping a repeat of
+    "ab" then "cd" is usually the real problem, not whatever comes after the repeat (see `parse_furthest`).
 */
-type Result<'a, T> = Option<(T, &'a str)>;
+mod error;
+pub use error::*;
+mod span;
+pub use span::*;
+mod adapters;
+pub use adapters::*;
+mod input;
+pub use input::*;
+mod numeric;
+pub use numeric::*;
+mod pratt;
+pub use pratt::*;
+mod recursive;
+pub use recursive::*;
+mod derive;
+pub use derive::*;
+mod scan;
+pub use scan::*;
+mod pattern;
+pub use pattern::*;
+mod stream;
+pub use stream::*;
+mod trace;
+pub use trace::*;
+mod memo;
+pub use memo::*;
+mod grammar;
+pub use grammar::*;
+mod keywords;
+pub use keywords::*;
+pub mod bits;
+pub use crate::scan;
+
+use std::iter::FromIterator;
+use std::ops::{Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
+use std::sync::Arc;
+
+pub type Result<'a, T, I = &'a str> = std::result::Result<(T, I), Error>;
+
+/// Represents something capable of producing a T from an input, which is a string unless you say otherwise
+/// (see `Input` for the alternatives). Call parse_result to get Ok(T) if the parse succeeded, or a ParseError
+/// saying where and why it didn't
+pub trait Parser<'a, T, I: Input<'a> = &'a str> {
+    fn parse(&self, input: I) -> Result<'a, T, I>;
+
+    /// like `parse`, but also merges the failures it passes over on the way into `passed`, like whatever stopped
+    /// a `repeat`, or the thing an `opt` didn't find. sequences like `then` hand the same `passed` to each of
+    /// their parsers, and if one fails, merge it into the error, since whatever stopped the repeat before it
+    /// might well have been the real problem. most parsers never pass over anything, so by default this is `parse`
+    /// ```
+    /// use lib::parse::*;
+    /// let pairs = "ab".then("cd").repeat(Any);
+    /// let mut passed = None;
+    /// assert_eq!(pairs.parse_furthest("abcdabce", &mut passed), Ok((vec![("ab", "cd")], "abce")));
+    /// assert_eq!(passed.map(|e| e.remaining()), Some(2));
+    ///
+    /// let err = pairs.then(eof()).parse_result("abcdabce").unwrap_err();
+    /// assert_eq!((err.offset, err.expected), (6, vec![Expected::literal("cd")]));
+    /// ```
+    fn parse_furthest(&self, input: I, _passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parse(input)
+    }
 
-/// Represents something capable of producing a T from an input string
-/// Call parse_result to get Some(T) if the parse succeeded or None if not
-pub trait Parser<'a, T> {
-    fn parse(&self, input: &'a str) -> Result<'a, T>;
+    /// parses a T off the front of `input`, ignoring anything after it
+    fn parse_result(&self, input: I) -> std::result::Result<T, ParseError> {
+        self.parse(input).map(|(val, _)| val).map_err(|e| input.locate(e))
+    }
 
-    fn parse_result(&self, input: &'a str) -> Option<T> {
-        self.parse(input).map(|(val, _)| val)
+    /// parses a T out of all of `input`, which is an error if there's anything left over. the error is at the
+    /// start of the leftovers, and says what they were
+    /// ```
+    /// use lib::parse::*;
+    /// let numbers = uint32().repeat_delimited(Many, "\n");
+    /// assert_eq!(numbers.parse_complete("1\n2"), Ok(vec![1, 2]));
+    ///
+    /// let err = numbers.parse_complete("1\n2\nthree\n4").unwrap_err();
+    /// assert_eq!((err.offset, err.line), (3, 2));
+    /// assert_eq!(err.to_string().lines().next(), Some("error: expected end of input, found \"\\nthree\\n4\""));
+    /// ```
+    fn parse_complete(&self, input: I) -> std::result::Result<T, ParseError> {
+        self.parse_all(input, Trailing::Nothing)
+    }
+
+    /// like `parse_complete`, but with a choice of what's fine to leave over
+    /// ```
+    /// use lib::parse::*;
+    /// assert_eq!(uint32().parse_all("12\r\n", Trailing::Newline), Ok(12));
+    /// assert!(uint32().parse_all("12\n\n", Trailing::Newline).is_err());
+    /// assert!(uint32().parse_all("12\n", Trailing::Nothing).is_err());
+    /// ```
+    fn parse_all(&self, input: I, trailing: Trailing) -> std::result::Result<T, ParseError> {
+        let (val, rest) = self.parse(input).map_err(|e| input.locate(e))?;
+        match trailing {
+            _ if rest.input_len() == 0 => Ok(val),
+            Trailing::Newline if rest.is_line_ending() => Ok(val),
+            _ => {
+                let mut e = input.locate(Error::new(rest, Expected::named("end of input")));
+                e.found = Some(rest.leftover());
+                Err(e)
+            }
+        }
     }
 
     /// transforms the results of this parser
-    fn map<U>(self, f: impl Fn(T)->U + 'a) -> BoxedParser<'a, U> where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let (val, rest) = self.parse(input)?;
-            Some((f(val), rest))
-        })
+    fn map<U, F: Fn(T)->U>(self, f: F) -> Map<Self, F, T> where Self: Sized {
+        Map::new(self, f)
     }
 
-    fn map_into<U: From<T>>(self) -> BoxedParser<'a, U> where Self: Sized+'a {
-        self.map(|v| v.into())
+    fn map_into<U: From<T>>(self) -> Map<Self, fn(T)->U, T> where Self: Sized {
+        self.map(U::from)
     }
 
     // nom calls this `a.flat_map(f)`, bodil calls this `and_then(a, f)`, haskell calls it `a >>= f`,
     // and this makes Parser a monoid in the category of endofunctors, as the kids say
     /// uses the results of this parser to make a new one.
-    fn and_then<U, P2: Parser<'a, U>>(self, f: impl Fn(T) -> P2 + 'a) -> BoxedParser<'a, U> where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let (val, rest) = self.parse(input)?;
-            f(val).parse(rest)
-        })
+    fn and_then<U, P2: Parser<'a, U, I>, F: Fn(T) -> P2>(self, f: F) -> AndThen<Self, F, T> where Self: Sized {
+        AndThen::new(self, f)
     }
 
     // nom calls this `a.and_then(b)` or `terminated(a, b)`, bodil calls this `left(a, b)`, but I think neither of those is intuitive
     // also, haskell calls this `a >> b`
     /// runs this, and skips over the results of the given Parser. Opposite of `but_really`. `a.skip(b)` yields `A`
-    fn skip<U>(self, second: impl Parser<'a, U> + 'a) -> BoxedParser<'a, T> where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let (val1, next) = self.parse(input)?;
-            let (_val2, rest) = second.parse(next)?;
-            Some((val1, rest))
-        })
+    fn skip<U, P2: Parser<'a, U, I>>(self, second: P2) -> Skip<Self, P2, U> where Self: Sized {
+        Skip::new(self, second)
     }
 
     // nom calls this `preceded(a, b)`, bodil calls this `right(a, b)`
     /// throws away this result, and keeps the second. Opposite of `skip`. `a.but_really(b)` yields `b`
-    fn but_really<U>(self, second: impl Parser<'a, U> + 'a) -> BoxedParser<'a, U> where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let (_val1, next) = self.parse(input)?;
-            let (val2, rest) = second.parse(next)?;
-            Some((val2, rest))
-        })
+    fn but_really<U, P2: Parser<'a, U, I>>(self, second: P2) -> ButReally<Self, P2, T> where Self: Sized {
+        ButReally::new(self, second)
     }
 
     // nom calls this `a.and(b)`, bodil calls this `pair(a, b)` but neither makes sense as a method name
-    /// captures the results of a second parser in a tuple of (T, U). `a.then(b)` yields `(a, b)`
-    fn then<U>(self, second: impl Parser<'a, U> + 'a) -> BoxedParser<'a, (T, U)> where Self: Sized + 'a {
-        BoxedParser::new(move |input| {
-            let (val1, next) = self.parse(input)?;
-            let (val2, rest) = second.parse(next)?;
-            Some(((val1, val2), rest))
-        })
-    }
-
-    /// falls back to a second parser if the first doesn't pan out
-    fn or(self, second: impl Parser<'a, T> + 'a) -> BoxedParser<'a, T> where Self: Sized + 'a {
-        BoxedParser::new(move |input| {
-            self.parse(input).or_else(|| second.parse(input))
-        })
-    }
-
-    /// rejects the result of this parser if it doesn't match the predicate
-    fn filter(self, f: impl Fn(&T)->bool + 'a) -> BoxedParser<'a, T>  where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let (val, rest) = self.parse(input)?;
-            if f(&val) {
-                Some((val, rest))
-            } else {
-                None
-            }
-        })
+    /// captures the results of a second parser in a tuple of (T, U). `a.then(b)` yields `(a, b)`.
+    /// if the second parser fails, its error gets merged with anything the first passed over (see `parse_furthest`),
+    /// and whichever got further wins
+    fn then<U, P2: Parser<'a, U, I>>(self, second: P2) -> Then<Self, P2> where Self: Sized {
+        Then::new(self, second)
+    }
+
+    /// falls back to a second parser if the first doesn't pan out. if neither does, we report
+    /// whichever got further, or what both of them expected if they gave up at the same spot
+    /// ```
+    /// use lib::parse::*;
+    /// let parser = "ab".or("c");
+    /// assert_eq!(parser.parse("abc"), Ok(("ab", "c")));
+    /// assert_eq!(parser.parse("cab"), Ok(("c", "ab")));
+    ///
+    /// let err = parser.parse_result("xyz").unwrap_err();
+    /// assert_eq!(err.expected, vec![Expected::literal("ab"), Expected::literal("c")]);
+    /// ```
+    fn or<P2: Parser<'a, T, I>>(self, second: P2) -> Or<Self, P2> where Self: Sized {
+        Or::new(self, second)
+    }
+
+    /// rejects the result of this parser if it doesn't match the predicate. all the error can say about that
+    /// is that it wanted a value matching the filter, so `named` is worth adding to say what it actually wanted
+    /// ```
+    /// use lib::parse::*;
+    /// let even = uint32().filter(|n| n % 2 == 0);
+    /// assert_eq!(even.parse("12"), Ok((12, "")));
+    ///
+    /// let err = even.parse_result("13").unwrap_err();
+    /// assert_eq!(err.to_string().lines().next(), Some("error: expected value matching filter, found '1'"));
+    ///
+    /// let err = even.named("even number").parse_result("13").unwrap_err();
+    /// assert_eq!(err.to_string().lines().next(), Some("error: expected even number, found '1'"));
+    /// ```
+    fn filter<F: Fn(&T)->bool>(self, f: F) -> Filter<Self, F> where Self: Sized {
+        Filter::new(self, f)
     }
 
-    /// runs this parser multiple times (according to `rep`), collecting its results into a Vec
+    /// pairs the result of this parser with the span of input it consumed.
+    /// see `Span` for turning that back into offsets, lines and text
+    /// ```
+    /// use lib::parse::*;
+    /// let input = "ecl:gry\n\nbyr:1937 hgt:\n\niyr:2013";
+    /// let passport = identifier_str().skip(":").then(take_while(|c| !c.is_whitespace())).repeat_delimited(Many, " ".or("\n"));
+    /// let batch = passport.spanned().repeat_delimited(Many, "\n\n");
+    ///
+    /// let (_, bad) = batch.parse_result(input).unwrap().into_iter()
+    ///     .find(|(fields, _)| fields.iter().any(|(_, value)| value.is_empty()))
+    ///     .unwrap();
+    /// assert_eq!(bad.start_line_col(input).line, 3);
+    /// assert_eq!(bad.slice(input), "byr:1937 hgt:");
+    /// ```
+    fn spanned(self) -> Spanned<Self> where Self: Sized {
+        Spanned::new(self)
+    }
+
+    /// gives this parser a name, so that when it fails without getting anywhere, the error says
+    /// we expected `name`, rather than whatever this parser happens to be built out of
+    /// ```
+    /// use lib::parse::*;
+    /// let parser = character().filter(|c| c.is_ascii_hexdigit()).named("hex digit");
+    /// let err = parser.parse_result("xyz").unwrap_err();
+    /// assert_eq!(err.expected, vec![Expected::named("hex digit")]);
+    /// ```
+    fn named(self, name: &'static str) -> Named<Self> where Self: Sized {
+        Named::new(self, name)
+    }
+
+    /// records this parser as `name` in the trace, when one is being collected (see `collect_trace`), without
+    /// changing how it works otherwise. `.named()` parsers are always traced too
+    fn trace(self, name: &'static str) -> Traced<Self> where Self: Sized {
+        Traced::new(self, name)
+    }
+
+    /// describes what this parser matches, as a tree of literals, sequences, choices, repetitions and named
+    /// rules. see `Grammar` for writing that out as EBNF, or making up input that matches it.
+    /// plain functions can't see inside themselves, so unless they've been given a `.describe()`, they're Opaque
+    /// ```
+    /// use lib::parse::*;
+    /// let sign = "+".or("-");
+    /// assert_eq!(sign.then(uint32()).grammar().to_ebnf(), "\
+    /// start ::= (\"+\" | \"-\") unsigned_integer
+    /// unsigned_integer ::= /[0-9]{1,9}/
+    /// ");
+    /// ```
+    fn grammar(&self) -> Grammar {
+        Grammar::Opaque
+    }
+
+    /// gives this parser a grammar to describe itself with, for when it can't work one out on its own, like a
+    /// closure. `grammar` only gets called when something asks for the grammar
+    /// ```
+    /// use lib::parse::*;
+    /// let vowel = character().filter(|c| "aeiou".contains(*c)).describe(|| Grammar::pattern("[aeiou]"));
+    /// assert_eq!(vowel.grammar().to_ebnf(), "start ::= /[aeiou]/\n");
+    /// ```
+    fn describe<G: Fn() -> Grammar>(self, grammar: G) -> Described<Self, G> where Self: Sized {
+        Described::new(self, grammar)
+    }
+
+    /// remembers how this parser did at each place in the input, so trying it again at the same place is
+    /// free. clones share what they remember, so use one in each alternative that would otherwise redo it
+    /// ```
+    /// use lib::parse::*;
+    ///
+    /// let input = format!("{}1{}", "(".repeat(20), ")".repeat(20));
+    ///
+    /// // sums like "((1+2))+3", where every level of parentheses tries its `term` twice
+    /// let mut terms = None;
+    /// let sum = recursive(|sum| {
+    ///     let term = "(".but_really(sum).skip(")").or(uint32()).memoize();
+    ///     terms = Some(term.clone());
+    ///     term.clone().skip("+").then(term.clone()).map(|(a, b)| a + b).or(term)
+    /// });
+    /// assert_eq!(sum.parse(&input), Ok((1, "")));
+    ///
+    /// // without memoizing, that would have been over a million tries
+    /// let terms = terms.unwrap();
+    /// assert_eq!((terms.misses(), terms.hits()), (21, 21));
+    /// ```
+    fn memoize(self) -> Memoize<Self, T, I> where Self: Sized, T: Clone {
+        Memoize::new(self)
+    }
+
+    /// runs this parser multiple times (according to `rep`), collecting its results into a Vec. once it's made
+    /// the minimum, a match that doesn't consume anything ends the repetition, rather than going on forever
     /// ```
     /// use lib::parse::*;
     /// let twice = "a".or("b").repeat(2);
-    /// assert_eq!(twice.parse("ab"), Some((vec!["a", "b"], "")));
-    /// assert_eq!(twice.parse("aaaa"), Some((vec!["a", "a"], "aa")));
-    /// assert_eq!(twice.parse("a"), None);
+    /// assert_eq!(twice.parse("ab"), Ok((vec!["a", "b"], "")));
+    /// assert_eq!(twice.parse("aaaa"), Ok((vec!["a", "a"], "aa")));
+    /// assert!(twice.parse("a").is_err());
     /// 
     /// let any = "a".or("b").repeat(Any);
-    /// assert_eq!(any.parse("ab"), Some((vec!["a", "b"], "")));
-    /// assert_eq!(any.parse("abaab"), Some((vec!["a", "b", "a", "a", "b"], "")));
-    /// assert_eq!(any.parse("xyz"), Some((vec![], "xyz")));
-    /// assert_eq!(any.parse(""), Some((vec![], "")));
+    /// assert_eq!(any.parse("ab"), Ok((vec!["a", "b"], "")));
+    /// assert_eq!(any.parse("abaab"), Ok((vec!["a", "b", "a", "a", "b"], "")));
+    /// assert_eq!(any.parse("xyz"), Ok((vec![], "xyz")));
+    /// assert_eq!(any.parse(""), Ok((vec![], "")));
+    ///
+    /// let some = literal("a").repeat(2..=3);
+    /// assert_eq!(some.parse("aaaa"), Ok((vec!["a", "a", "a"], "a")));
+    /// assert!(some.parse("ab").is_err());
     /// ```
-    fn repeat(self, rep: impl Into<Repetition> + 'a) -> BoxedParser<'a, Vec<T>> where Self: Sized+'a, T:'a {
-        self.repeat_delimited(rep, succeed)
+    fn repeat(self, rep: impl Into<Repetition>) -> RepeatDelimited<Self, Succeed, ()> where Self: Sized {
+        self.repeat_delimited(rep, Succeed)
     }
 
     /// consumes instances of this parser delimited by the given separator parser.
+    /// if we can't reach the minimum number of repetitions, the error is whichever of the
+    /// separator or the item failed first
     /// ```
     /// use lib::parse::*;
     /// let parser = ["foo", "bar", "baz"].repeat_delimited(Many, ",");
-    /// assert_eq!(parser.parse("foo,bar,baz"), Some((vec!["foo", "bar", "baz"], "")));
-    /// assert_eq!(parser.parse("foo,bar,baz,"), Some((vec!["foo", "bar", "baz"], ",")));
-    /// assert_eq!(parser.parse("foo,bar baz"), Some((vec!["foo", "bar"], " baz")));
-    /// assert_eq!(parser.parse("foo,dog,cat"), Some((vec!["foo"], ",dog,cat")));
-    /// assert_eq!(parser.parse("dog,cat,bird"), None);
+    /// assert_eq!(parser.parse("foo,bar,baz"), Ok((vec!["foo", "bar", "baz"], "")));
+    /// assert_eq!(parser.parse("foo,bar,baz,"), Ok((vec!["foo", "bar", "baz"], ",")));
+    /// assert_eq!(parser.parse("foo,bar baz"), Ok((vec!["foo", "bar"], " baz")));
+    /// assert_eq!(parser.parse("foo,dog,cat"), Ok((vec!["foo"], ",dog,cat")));
+    /// assert!(parser.parse("dog,cat,bird").is_err());
     /// 
     /// let parser = ["a","b"].repeat_delimited(2, ",");
-    /// assert_eq!(parser.parse("a,b"), Some((vec!["a","b"], "")));
-    /// assert_eq!(parser.parse("a,b,a,b"), Some((vec!["a","b"], ",a,b")));
-    /// assert_eq!(parser.parse("a"), None);
-    /// assert_eq!(parser.parse("a,c"), None);
+    /// assert_eq!(parser.parse("a,b"), Ok((vec!["a","b"], "")));
+    /// assert_eq!(parser.parse("a,b,a,b"), Ok((vec!["a","b"], ",a,b")));
+    /// assert_eq!(parser.parse_result("a").unwrap_err().expected, vec![Expected::literal(",")]);
+    /// assert_eq!(parser.parse_result("a,c").unwrap_err().offset, 2);
     /// 
     /// let parser = ["a", "b"].repeat_delimited(Any, ",");
-    /// assert_eq!(parser.parse("a,b"), Some((vec!["a","b"], "")));
-    /// assert_eq!(parser.parse("x,y"), Some((vec![], "x,y")));
+    /// assert_eq!(parser.parse("a,b"), Ok((vec!["a","b"], "")));
+    /// assert_eq!(parser.parse("x,y"), Ok((vec![], "x,y")));
     /// 
     /// //let parser = "x".repeat_delimited(Any, ["y", "z"]);
-    /// //assert_eq!(parser.parse("xyxzx"), Some((vec!["x", "x", "x"], "")))
-    /// ```
-    fn repeat_delimited<U>(self, rep: impl Into<Repetition> + 'a, sep: impl Parser<'a, U> + 'a) -> BoxedParser<'a, Vec<T>> where Self: Sized+'a, T:'a {
-        let rep = rep.into();
-        BoxedParser::new(move |input| {
-            let mut input = input;
-            let mut result = Vec::new();
-            let mut is_first = true;
-
-            // until we hit the minimum, we MUST match
-            for _ in 0..rep.min() {
-                if !is_first {
-                    let (_, rest) = sep.parse(input)?;
-                    input = rest;
-                }
-
-                let (val, rest) = self.parse(input)?;
-                input = rest;
-                result.push(val);
-                is_first = false;
-            }
+    /// //assert_eq!(parser.parse("xyxzx"), Ok((vec!["x", "x", "x"], "")))
+    /// ```
+    fn repeat_delimited<U, S: Parser<'a, U, I>>(self, rep: impl Into<Repetition>, sep: S) -> RepeatDelimited<Self, S, U> where Self: Sized {
+        RepeatDelimited::new(self, rep.into(), sep)
+    }
 
-            if rep.met_or_exceeded_by(result.len()) {
-                return Some((result, input));
-            }
+    /// runs this parser multiple times (according to `rep`), collecting its results into any collection
+    /// at all, without building a Vec first
+    /// ```
+    /// use lib::parse::*;
+    /// use std::collections::HashSet;
+    ///
+    /// let letters = character().filter(char::is_ascii_lowercase).repeat_collect::<HashSet<_>>(1..);
+    /// assert_eq!(letters.parse("abac\n"), Ok((HashSet::from(['a', 'b', 'c']), "\n")));
+    ///
+    /// let shouting = character().filter(char::is_ascii_uppercase).repeat_collect::<String>(..=3);
+    /// assert_eq!(shouting.parse("HELLO"), Ok(("HEL".to_string(), "LO")));
+    /// ```
+    fn repeat_collect<C: FromIterator<T>>(self, rep: impl Into<Repetition>) -> RepeatCollect<Self, T, C> where Self: Sized {
+        RepeatCollect::new(self, rep.into())
+    }
 
-            // keep parsing until we hit the maximum, or we no longer match
-            loop {
-                let mut i = input;
-                if !is_first {
-                    if let Some((_, rest)) = sep.parse(input) {
-                        i = rest;
-                    } else {
-                        break
-                    }
-                }
+    /// runs this parser multiple times (according to `rep`), combining the results as it goes, starting
+    /// with `init`, like `Iterator::fold`. nothing gets collected, so nothing gets allocated
+    /// ```
+    /// use lib::parse::*;
+    ///
+    /// // which letters show up, as a set of bits
+    /// let letters = character().filter(char::is_ascii_lowercase)
+    ///     .fold_many(Many, 0u32, |set, c| set | 1 << (c as u32 - 'a' as u32));
+    /// assert_eq!(letters.parse("abac"), Ok((0b111, "")));
+    /// assert!(letters.parse("ABC").is_err());
+    /// ```
+    fn fold_many<A: Clone, F: Fn(A, T) -> A>(self, rep: impl Into<Repetition>, init: A, f: F) -> FoldMany<Self, T, A, F> where Self: Sized {
+        FoldMany::new(self, rep.into(), init, f)
+    }
 
-                if let Some((val, rest)) = self.parse(i) {
-                    input = rest;
-                    result.push(val);
-                    is_first = false;
-                } else {
-                    break
-                }
-                if rep.met_or_exceeded_by(result.len()) {
-                    break
-                }
-            }
-            Some((result, input))
-        })
+    /// runs this parser multiple times (according to `rep`), and says how many times it matched
+    /// ```
+    /// use lib::parse::*;
+    /// let indent = " ".count_many(Any);
+    /// assert_eq!(indent.parse("    x"), Ok((4, "x")));
+    /// assert_eq!(indent.parse("x"), Ok((0, "x")));
+    /// ```
+    fn count_many(self, rep: impl Into<Repetition>) -> CountMany<Self, T> where Self: Sized {
+        FoldMany::new(self, rep.into(), 0, |n, _| n + 1)
     }
 
     // nom calls a similar operation `many_till(a, b)`, but this doesn't return a pair
     /// runs this parser until the other parser matches, but does not consume the second parser's input.
-    /// think of it like a non-greedy repeat(Any)
+    /// think of it like a non-greedy repeat(Any). when neither matches, we expected either of them.
     /// ```
-    /// use lib::parse::Parser;
+    /// use lib::parse::*;
     /// let parser = "abc".up_until("end");
-    /// assert_eq!(parser.parse("abcabcend"), Some((vec!["abc", "abc"], "end")));
-    /// assert_eq!(parser.parse("abc123end"), None);
-    /// assert_eq!(parser.parse("123123end"), None);
-    /// assert_eq!(parser.parse(""), None);
-    /// assert_eq!(parser.parse("abcendefg"), Some((vec!["abc"], "endefg")));
-    /// ```
-    fn up_until<U>(self, other: impl Parser<'a, U> + 'a) -> BoxedParser<'a, Vec<T>> where Self: Sized+'a {
-        BoxedParser::new(move |input| {
-            let mut input = input;
-            let mut result = Vec::new();
-
-            // implementation strongly influenced by nom::multi::many_till (but different, and way less verbose)
-            loop {
-                // first check the second parser, if we match, we're done
-                if let Some((_val2, _rest)) = other.parse(input) {
-                    return Some((result, input));
-                }
-                // if we didn't match, run the first parser and collect its result
-                let (val, next) = self.parse(input)?;
-                result.push(val);
-                input = next;
-            }
-        })
+    /// assert_eq!(parser.parse("abcabcend"), Ok((vec!["abc", "abc"], "end")));
+    /// assert!(parser.parse("abc123end").is_err());
+    /// assert!(parser.parse("123123end").is_err());
+    /// assert!(parser.parse("").is_err());
+    /// assert_eq!(parser.parse("abcendefg"), Ok((vec!["abc"], "endefg")));
+    ///
+    /// let err = parser.parse_result("abc123end").unwrap_err();
+    /// assert_eq!(err.offset, 3);
+    /// assert_eq!(err.expected, vec![Expected::literal("abc"), Expected::literal("end")]);
+    /// ```
+    fn up_until<U, P2: Parser<'a, U, I>>(self, other: P2) -> UpUntil<Self, P2, U> where Self: Sized {
+        UpUntil::new(self, other)
+    }
+
+    /// erases the type of this parser, behind a Box. every other combinator builds up a concrete
+    /// (and often enormous) type, which is great for speed but can't be named in a struct field,
+    /// or returned from two branches of an `if`. it can't be cloned or sent to another thread, see `shared` for that
+    /// ```
+    /// use lib::parse::*;
+    /// let sign = |negative: bool| if negative { "-".boxed() } else { "+".or("").boxed() };
+    /// assert_eq!(sign(true).parse("-1"), Ok(("-", "1")));
+    /// assert_eq!(sign(false).parse("1"), Ok(("", "1")));
+    /// ```
+    fn boxed(self) -> BoxedParser<'a, T, I> where Self: Sized+'a {
+        BoxedParser::new(self)
+    }
+
+    /// borrows this parser, so it can go into another combinator without being used up, and still be used
+    /// (or borrowed again) afterwards
+    /// ```
+    /// use lib::parse::*;
+    /// let number = integer::<i32>().named("number");
+    /// let range = number.by_ref().skip("..").then(number.by_ref());
+    /// assert_eq!(range.parse("-3..4"), Ok(((-3, 4), "")));
+    /// assert_eq!(number.parse("7"), Ok((7, "")));
+    /// ```
+    fn by_ref(&self) -> ByRef<'_, Self> where Self: Sized {
+        ByRef::new(self)
+    }
+
+    /// erases the type of this parser, like `boxed`, but behind an Arc, so clones are cheap and share the
+    /// same parser, and it can be sent to other threads. anything that's Sync can already be borrowed from a
+    /// scoped thread, so this is for when it needs owning, or a name
+    /// ```
+    /// use lib::parse::*;
+    ///
+    /// let entry = uint32().skip("-").then(uint32()).map(|(lo, hi)| hi - lo).shared();
+    /// let lines = "1-3\n4-9\n2-2\n10-20\n7-8".lines().collect::<Vec<_>>();
+    /// let total = std::thread::scope(|s| {
+    ///     let workers = lines.chunks(2).map(|chunk| {
+    ///         let entry = entry.clone();
+    ///         s.spawn(move || chunk.iter().map(|line| entry.parse_complete(line).unwrap()).sum::<u32>())
+    ///     }).collect::<Vec<_>>();
+    ///     workers.into_iter().map(|w| w.join().unwrap()).sum::<u32>()
+    /// });
+    /// assert_eq!(total, 2 + 5 + 0 + 10 + 1);
+    /// ```
+    fn shared(self) -> SharedParser<'a, T, I> where Self: Sized + Send + Sync + 'a {
+        SharedParser { parser: Arc::new(self) }
     }
 }
 
-pub struct BoxedParser<'a, T> {
-    parser: Box<dyn Parser<'a, T> + 'a>,
+pub struct BoxedParser<'a, T, I = &'a str> {
+    parser: Box<dyn Parser<'a, T, I> + 'a>,
 }
 
-impl<'a, T> BoxedParser<'a, T> {
-    fn new(parser: impl Parser<'a, T> + 'a) -> Self {
+impl<'a, T, I: Input<'a>> BoxedParser<'a, T, I> {
+    fn new(parser: impl Parser<'a, T, I> + 'a) -> Self {
         Self { parser: Box::new(parser) }
     }
 }
 
 /// all BoxedParsers are Parsers
-impl<'a, T> Parser<'a, T> for BoxedParser<'a, T> {
-    fn parse(&self, input: &'a str) -> Result<'a, T> {
+impl<'a, T, I: Input<'a>> Parser<'a, T, I> for BoxedParser<'a, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
         self.parser.parse(input)
     }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parser.parse_furthest(input, passed)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
 }
 
-/// all functions of &str->Result are Parsers
-impl<'a, T, F: Fn(&'a str)->Result<T>> Parser<'a, T> for F {
-    fn parse(&self, input: &'a str) -> Result<'a, T> {
-        self(input)
+/// A Parser behind an Arc, made by `Parser::shared`. It can be cloned, sent and shared between threads
+pub struct SharedParser<'a, T, I = &'a str> {
+    parser: Arc<dyn Parser<'a, T, I> + Send + Sync + 'a>,
+}
+
+impl<'a, T, I> Clone for SharedParser<'a, T, I> {
+    fn clone(&self) -> Self {
+        Self { parser: self.parser.clone() }
     }
 }
 
+impl<'a, T, I: Input<'a>> Parser<'a, T, I> for SharedParser<'a, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.parser.parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parser.parse_furthest(input, passed)
+    }
 
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+/// all functions of Input->Result are Parsers
+impl<'a, T, I: Input<'a>, F: Fn(I)->Result<'a, T, I>> Parser<'a, T, I> for F {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self(input)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Repetition {
     /// Zero times
     Never,
@@ -307,89 +558,479 @@ impl From<usize> for Repetition {
     fn from(n: usize) -> Self { Exactly(n) }
 }
 
+impl Repetition {
+    /// the Repetition for however many times are in `range`, so `2..=5` is `Between(2, 5)`, and `1..` is `Many`
+    pub fn from_range(range: impl RangeBounds<usize>) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&n) => Some(n),
+            // `..0` can't ever be met, and zero times is the closest we can get
+            Bound::Excluded(&n) => Some(n.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+        match (min, max) {
+            (min, Some(max)) if min > max => panic!("can't repeat between {} and {} times", min, max),
+            (0, Some(0)) => Never,
+            (0, None) => Any,
+            (1, None) => Many,
+            (min, None) => AtLeast(min),
+            (0, Some(max)) => AtMost(max),
+            (min, Some(max)) if min == max => Exactly(min),
+            (min, Some(max)) => Between(min, max),
+        }
+    }
+}
+
+/// ranges can be used for however many times they include, like `2..=5`, `1..` or `..3`
+macro_rules! impl_repetition_from_range {
+    ($($range:ty),*) => {
+        $(
+            impl From<$range> for Repetition {
+                fn from(range: $range) -> Self { Repetition::from_range(range) }
+            }
+        )*
+    };
+}
+
+impl_repetition_from_range!(Range<usize>, RangeInclusive<usize>, RangeFrom<usize>, RangeTo<usize>, RangeToInclusive<usize>, RangeFull);
+
+/// What `Parser::parse_all` is fine with having left over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trailing {
+    /// nothing at all
+    Nothing,
+    /// a single line ending, `\n` or `\r\n`, like the one at the end of most files
+    Newline,
+}
+
 /// A Parser that matches any one single character, and returns it
-pub fn character<'a>() -> impl Parser<'a, char> {
-    move |input: &'a str| input.chars().next().map(|c| (c, &input[c.len_utf8()..]))
+pub fn character<'a>() -> impl Parser<'a, char> + Clone {
+    let character = move |input: &'a str| match input.chars().next() {
+        Some(c) => Ok((c, &input[c.len_utf8()..])),
+        None => Err(Error::new(input, Expected::named("any character"))),
+    };
+    character.describe(|| Grammar::pattern("."))
 }
 
 /// Creates a Parser that matches a specific string and returns it
-pub fn literal<'a>(expected: impl AsRef<str>) -> impl Parser<'a, &'a str> {
-    move |input: &'a str| {
+pub fn literal<'a>(expected: impl AsRef<str> + Clone) -> impl Parser<'a, &'a str> + Clone {
+    let text = expected.as_ref().to_string();
+    let literal = move |input: &'a str| {
         let expected = expected.as_ref();
         input
             .get(0..expected.len())
             .filter(|s| *s == expected)
             .map(|s| (s, &input[expected.len()..]))
-    }
+            .ok_or_else(|| Error::new(input, Expected::literal(expected)))
+    };
+    literal.describe(move || Grammar::literal(text.clone()))
 }
 
 /// all static strings are interpreted as `literal("...")`
 impl<'a> Parser<'a, &'a str> for &'static str {
     fn parse(&self, input: &'a str) -> Result<'a, &'a str> {
-        literal(self).parse(input)
+        // same as literal(self), but we can report failures without copying ourselves into a String
+        match input.get(0..self.len()) {
+            Some(s) if s == *self => Ok((s, &input[self.len()..])),
+            _ => Err(Error::new(input, Expected::static_literal(self))),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::literal(*self)
     }
 }
 
-// fails to match anything
-fn fail<T>(_: &str) -> Result<'_, T> {
-    None
+/// A Parser that successfully matches zero characters
+#[derive(Debug, Clone, Copy)]
+pub struct Succeed;
+
+impl<'a, I: Input<'a>> Parser<'a, (), I> for Succeed {
+    fn parse(&self, input: I) -> Result<'a, (), I> {
+        Ok(((), input))
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::empty()
+    }
 }
-// successfully matches zero characters
-fn succeed(input: &str) -> Result<'_, ()> {
-    Some(((), input))
+
+/// A Parser that matches any one item of any kind of input, and returns it.
+/// for text, that's the same as `character()`, but for bytes it's a byte, and so on
+/// ```
+/// use lib::parse::*;
+/// // a day5 boarding pass, read as bits straight off the bytes
+/// let bit = any().filter(|b: &u8| b"FBLR".contains(b)).map(|b| (b == b'B' || b == b'R') as usize);
+/// let seat = bit.repeat(10).map(|bits| bits.iter().fold(0, |n, b| n << 1 | b));
+/// assert_eq!(seat.parse_result(&b"FBFBBFFRLR"[..]), Ok(357));
+///
+/// let err = seat.parse_result(&b"FBFBBFFRLX"[..]).unwrap_err();
+/// assert_eq!((err.offset, err.found), (9, Some("'X'".to_string())));
+/// ```
+pub fn any<'a, I: Input<'a>>() -> impl Parser<'a, I::Item, I> + Clone {
+    move |input: I| match input.next_item() {
+        Some((item, rest)) => Ok((item, rest)),
+        None => Err(Error::new(input, Expected::named("anything"))),
+    }
 }
 
-/// slices of strings are Parsers, representing alternatives
+/// Creates a Parser that matches one specific item of any kind of input, like a byte or a token
+/// ```
+/// use lib::parse::*;
+/// let row = item(b'F').or(item(b'B')).repeat(7);
+/// assert_eq!(row.parse(&b"FBFBBFFRLR"[..]), Ok((b"FBFBBFF".to_vec(), &b"RLR"[..])));
+/// assert_eq!(row.parse_result(&b"FBFRBFFRLR"[..]).unwrap_err().offset, 3);
+/// ```
+pub fn item<'a, I: Input<'a>>(expected: I::Item) -> impl Parser<'a, I::Item, I> + Clone where I::Item: PartialEq + SliceItem {
+    move |input: I| match input.next_item() {
+        Some((item, rest)) if item == expected => Ok((item, rest)),
+        _ => Err(Error::new(input, Expected::named(expected.describe()))),
+    }
+}
+
+/// byte string literals are interpreted as matching those exact bytes
+/// ```
+/// use lib::parse::*;
+/// let parser = b"FB".then(b"L".or(b"R"));
+/// assert_eq!(parser.parse(&b"FBLR"[..]), Ok(((&b"FB"[..], &b"L"[..]), &b"R"[..])));
+/// assert!(parser.parse(&b"BF"[..]).is_err());
+/// ```
+impl<'a, const N: usize> Parser<'a, &'a [u8], &'a [u8]> for &'static [u8; N] {
+    fn parse(&self, input: &'a [u8]) -> Result<'a, &'a [u8], &'a [u8]> {
+        if input.starts_with(&self[..]) {
+            Ok(<[u8]>::split_at(input, N))
+        } else {
+            Err(Error::new(input, Expected::literal(String::from_utf8_lossy(&self[..]))))
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::literal(String::from_utf8_lossy(&self[..]).into_owned())
+    }
+}
+
+/// slices of strings are Parsers, representing alternatives. the longest one that matches wins, whatever
+/// order they're in. every one gets compared against the input every time, so for more than a few, see `keywords`
+/// ```
+/// use lib::parse::*;
+/// let parser = &["in", "int"][..];
+/// assert_eq!(parser.parse("int x"), Ok(("int", " x")));
+/// assert_eq!(parser.parse_result("x").unwrap_err().expected, vec![Expected::literal("in"), Expected::literal("int")]);
+/// ```
 impl<'a> Parser<'a, &'a str> for &'a [&'a str] {
     fn parse(&self, input: &'a str) -> Result<'a, &'a str> {
-        self.iter()
-            .fold(BoxedParser::new(fail), |acc, p| acc.or(literal(p)))
-            .parse(input)
+        match self.iter().filter(|s| input.starts_with(**s)).max_by_key(|s| s.len()) {
+            Some(s) => Ok(input.split_at(s.len())),
+            None => Err(self.iter()
+                .map(|s| Error::new(input, Expected::literal(*s)))
+                .fold(Error::unexpected(input), Error::merge)),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Choice(self.iter().map(|s| Grammar::literal(s.to_string())).collect())
     }
 }
 
-pub fn digit<'a>() -> impl Parser<'a, char> {
-    character().filter(|c| c.is_numeric())
+/// A Parser that only matches at the end of the input, and doesn't consume anything
+/// ```
+/// use lib::parse::*;
+/// let last = uint32().skip(eof());
+/// assert_eq!(last.parse("12"), Ok((12, "")));
+/// assert_eq!(last.parse_result("12 ").unwrap_err().expected, vec![Expected::named("end of input")]);
+/// ```
+pub fn eof<'a, I: Input<'a>>() -> impl Parser<'a, (), I> + Clone {
+    let eof = move |input: I| {
+        if input.input_len() == 0 {
+            Ok(((), input))
+        } else {
+            Err(Error::new(input, Expected::named("end of input")))
+        }
+    };
+    eof.describe(Grammar::empty)
+}
+
+/// A Parser for a sequence written out by hand, as a closure that takes somewhere to leave what it passes over
+/// along with the input, and hands that to each parser's `parse_furthest` in turn. Its errors are then as good
+/// as `then`'s, since whatever stopped a parser early gets merged in (see `parse_furthest`). It's what
+/// `#[derive(Parse)]` writes
+/// ```
+/// use lib::parse::*;
+/// let pairs = "ab".then("cd").repeat(Any);
+/// let by_hand = sequence(|input, passed| {
+///     let (pairs, input) = pairs.parse_furthest(input, passed)?;
+///     let (_, input) = eof().parse_furthest(input, passed)?;
+///     Ok((pairs, input))
+/// });
+/// let err = by_hand.parse_result("abcdabce").unwrap_err();
+/// assert_eq!(err, pairs.then(eof()).parse_result("abcdabce").unwrap_err());
+/// assert_eq!((err.offset, err.expected), (6, vec![Expected::literal("cd")]));
+/// ```
+pub fn sequence<'a, T, I: Input<'a>, F: Fn(I, &mut Option<Error>) -> Result<'a, T, I>>(f: F) -> Sequence<F> {
+    Sequence::new(f)
+}
+
+/// Runs `parser`, and gives back what it produced if it matched, or None without consuming anything if it didn't.
+/// Unlike `.repeat(AtMost(1))`, there's no Vec involved
+/// ```
+/// use lib::parse::*;
+/// let signed = opt("-").then(uint32());
+/// assert_eq!(signed.parse("-12"), Ok(((Some("-"), 12), "")));
+/// assert_eq!(signed.parse("12"), Ok(((None, 12), "")));
+/// assert!(signed.parse("+12").is_err());
+/// ```
+pub fn opt<'a, T, I: Input<'a>, P: Parser<'a, T, I>>(parser: P) -> Optional<P> {
+    Optional::new(parser)
+}
+
+/// Runs `parser`, and gives back what it produced, but without consuming anything, so whatever comes next
+/// starts from the same place. Fails if `parser` does
+/// ```
+/// use lib::parse::*;
+/// let parser = peek(uint32()).then(word());
+/// assert_eq!(parser.parse("12ab cd"), Ok(((12, "12ab".to_string()), " cd")));
+/// assert!(parser.parse("ab").is_err());
+/// ```
+pub fn peek<'a, T, I: Input<'a>, P: Parser<'a, T, I>>(parser: P) -> Peek<P> {
+    Peek::new(parser)
+}
+
+/// A Parser that only matches where `parser` doesn't, and never consumes anything. Like `peek`, but backwards
+/// ```
+/// use lib::parse::*;
+/// // a keyword, but not the start of a longer word
+/// let keyword = |k: &'static str| k.skip(not(character().filter(|c| c.is_alphanumeric())));
+/// assert_eq!(keyword("if").parse("if x"), Ok(("if", " x")));
+/// assert!(keyword("if").parse("iffy").is_err());
+///
+/// // everything but the last item of a list
+/// let not_last = uint32().skip(not(eof()));
+/// assert_eq!(not_last.parse("1,2"), Ok((1, ",2")));
+/// assert!(not_last.parse("2").is_err());
+/// ```
+pub fn not<'a, T, I: Input<'a>, P: Parser<'a, T, I>>(parser: P) -> Not<P, T> {
+    Not::new(parser)
+}
+
+/// Runs `parser`, and gives back `value` instead of what it produced, for when all that matters is that it
+/// matched
+/// ```
+/// use lib::parse::*;
+/// let seat = value("F", 0).or(value("B", 1)).repeat(7).map(|bits| bits.iter().fold(0, |n, b| n << 1 | b));
+/// assert_eq!(seat.parse("FBFBBFF"), Ok((44, "")));
+/// ```
+pub fn value<'a, T, U: Clone, I: Input<'a>>(parser: impl Parser<'a, T, I>, value: U) -> impl Parser<'a, U, I> {
+    parser.map(move |_| value.clone())
+}
+
+/// A Parser for a single decimal digit, 0-9. Other unicode numerals don't count
+pub fn digit<'a>() -> impl Parser<'a, char> + Clone {
+    character().filter(|c| c.is_ascii_digit()).describe(|| Grammar::pattern("[0-9]")).named("digit")
 }
 
 // 'u32' is already taken, guess we'll be more verbose
-pub fn uint32<'a>() -> impl Parser<'a, u32> {
-    digit()
-        .repeat(Many)
-        .map(|ds| ds.iter().fold(0, |n, d| n * 10 + d.to_digit(10).unwrap()))
+/// A Parser for a u32, which is an error if the number is too big. See `integer` for every other size
+pub fn uint32<'a>() -> impl Parser<'a, u32> + Clone {
+    integer::<u32>().named("unsigned integer")
 }
 
-pub fn whitespace<'a>() -> impl Parser<'a, char> {
-    character().filter(|c| c.is_whitespace())
+pub fn whitespace<'a>() -> impl Parser<'a, char> + Clone {
+    character().filter(|c| c.is_whitespace()).describe(|| Grammar::pattern(r"\s")).named("whitespace")
 }
 
-pub fn word<'a>() -> impl Parser<'a, String> {
-    character()
-        .filter(|c| c.is_alphanumeric() || *c == '_')
-        .repeat(Many)
-        .map(|v| v.into_iter().collect())
+/// A Parser for a run of letters, digits and underscores, as a String. See `word_str` to borrow it instead
+pub fn word<'a>() -> impl Parser<'a, String> + Clone {
+    word_str().map(String::from)
 }
 
-pub fn identifier<'a>() -> impl Parser<'a, String> {
-    character()
-        .filter(|c| c.is_alphabetic() || *c == '_')
-        .then(word())
-        .map(|(c, s)| c.to_string() + &s)
+/// A Parser for a run of letters, digits and underscores, borrowed from the input
+/// ```
+/// use lib::parse::*;
+/// assert_eq!(word_str().parse("pass_word1: x"), Ok(("pass_word1", ": x")));
+/// assert!(word_str().parse(" word").is_err());
+/// ```
+pub fn word_str<'a>() -> impl Parser<'a, &'a str> + Clone {
+    take_while1(|c| c.is_alphanumeric() || c == '_').describe(|| Grammar::pattern(r"\w+")).named("word")
 }
 
-// string runs the parser, collecting the traversed input into a string, then discarding the parser's internal result
-pub fn string<'a, T>(p: impl Parser<'a, T>) -> impl Parser<'a, String> {
-    move |input| {
-        let (_val, rest) = p.parse(input)?;
-        let consumed_len = input.len()-rest.len();
-        Some((input[0..consumed_len].to_string(), rest))
+/// A Parser for a letter or underscore, followed by any number of letters, digits and underscores, as a String.
+/// See `identifier_str` to borrow it instead
+pub fn identifier<'a>() -> impl Parser<'a, String> + Clone {
+    identifier_str().map(String::from)
+}
+
+/// A Parser for a letter or underscore, followed by any number of letters, digits and underscores, borrowed
+/// from the input
+/// ```
+/// use lib::parse::*;
+/// assert_eq!(identifier_str().parse("x1 = 2"), Ok(("x1", " = 2")));
+/// assert!(identifier_str().parse("1x").is_err());
+/// ```
+pub fn identifier_str<'a>() -> impl Parser<'a, &'a str> + Clone {
+    let first = character().filter(|c| c.is_alphabetic() || *c == '_');
+    recognize(first.then(take_while(|c| c.is_alphanumeric() || c == '_')))
+        .describe(|| Grammar::pattern(r"[A-Za-z_]\w*"))
+        .named("identifier")
+}
+
+/// A Parser for as many characters as match `pred`, even none at all, borrowed from the input
+/// ```
+/// use lib::parse::*;
+/// let spaces = take_while(|c| c == ' ');
+/// assert_eq!(spaces.parse("  x"), Ok(("  ", "x")));
+/// assert_eq!(spaces.parse("x"), Ok(("", "x")));
+/// ```
+pub fn take_while<'a>(pred: impl Fn(char) -> bool + Clone) -> impl Parser<'a, &'a str> + Clone {
+    move |input: &'a str| {
+        let n = input.find(|c| !pred(c)).unwrap_or(input.len());
+        Ok(input.split_at(n))
     }
 }
 
+/// A Parser for as many characters as match `pred`, borrowed from the input, which has to be at least one.
+/// Failing doesn't say what it expected, so give it a `.named()`
+/// ```
+/// use lib::parse::*;
+/// let hex = take_while1(|c| c.is_ascii_hexdigit()).named("hex digits");
+/// assert_eq!(hex.parse("c0ffee!"), Ok(("c0ffee", "!")));
+/// assert_eq!(hex.parse_result("xyz").unwrap_err().expected, vec![Expected::named("hex digits")]);
+/// ```
+pub fn take_while1<'a>(pred: impl Fn(char) -> bool + Clone) -> impl Parser<'a, &'a str> + Clone {
+    let many = take_while(pred);
+    move |input: &'a str| match many.parse(input)? {
+        ("", _) => Err(Error::unexpected(input)),
+        taken => Ok(taken),
+    }
+}
+
+/// A Parser for everything up to the first place `end` would match, without consuming what `end` matches.
+/// It's an error if `end` never matches at all
+/// ```
+/// use lib::parse::*;
+/// let comment = "/*".but_really(take_until("*/")).skip("*/");
+/// assert_eq!(comment.parse("/* a * b */ c"), Ok((" a * b ", " c")));
+///
+/// let err = comment.parse_result("/* a").unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected \"*/\", found end of input"));
+/// ```
+pub fn take_until<'a, T, I: Input<'a>>(end: impl Parser<'a, T, I>) -> impl Parser<'a, I, I> {
+    move |input: I| {
+        let mut rest = input;
+        loop {
+            match end.parse(rest) {
+                Ok(_) => return Ok(input.split_at(input.input_len() - rest.input_len())),
+                Err(e) => match rest.next_item() {
+                    Some((_, next)) => rest = next,
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+/// Runs `parser`, and gives back the input it consumed, rather than what it produced
+/// ```
+/// use lib::parse::*;
+/// let version = recognize(uint32().then(".".then(uint32()).repeat(Any)));
+/// assert_eq!(version.parse("1.22.3-beta"), Ok(("1.22.3", "-beta")));
+/// ```
+pub fn recognize<'a, T, I: Input<'a>, P: Parser<'a, T, I>>(parser: P) -> Recognize<P, T> {
+    Recognize::new(parser)
+}
+
+/// Runs `parser`, and copies the input it consumed into a String, rather than what it produced.
+/// See `recognize` to borrow it instead
+pub fn string<'a, T>(p: impl Parser<'a, T>) -> impl Parser<'a, String> {
+    recognize(p).map(String::from)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_repetition_from_range() {
+        assert_eq!(Repetition::from(..).range(), (0, None));
+        assert_eq!(Repetition::from(1..).range(), (1, None));
+        assert_eq!(Repetition::from(3..).range(), (3, None));
+        assert_eq!(Repetition::from(..3).range(), (0, Some(2)));
+        assert_eq!(Repetition::from(..=3).range(), (0, Some(3)));
+        assert_eq!(Repetition::from(2..5).range(), (2, Some(4)));
+        assert_eq!(Repetition::from(2..=2).range(), (2, Some(2)));
+        assert_eq!(Repetition::from(0..0).range(), (0, Some(0)));
+        assert!(std::panic::catch_unwind(|| Repetition::from_range((Bound::Included(3), Bound::Included(2)))).is_err());
+    }
+
+    #[test]
+    fn test_parse_complete_leftovers() {
+        let err = "a".parse_complete("abcdefghijklmnopqrstuvwxyz").unwrap_err();
+        assert_eq!(err.found.as_deref(), Some("\"bcdefghijklmnopqrstuvwxy…\""));
+
+        let err = item(b'F').parse_complete(&b"FB\n"[..]).unwrap_err();
+        assert_eq!((err.offset, err.found.as_deref()), (1, Some("\"B\\n\"")));
+        assert_eq!(item(b'F').skip(item(b'B')).parse_all(&b"FB\n"[..], Trailing::Newline), Ok(b'F'));
+    }
+
+    #[test]
+    fn test_lookahead_grammar_and_errors() {
+        let parser = opt("-").then(peek(digit())).skip(not("0")).then(uint32());
+        assert_eq!(parser.grammar().to_ebnf(), "start ::= \"-\"? unsigned_integer\nunsigned_integer ::= /[0-9]{1,9}/\n");
+        assert_eq!(parser.parse("-12"), Ok((((Some("-"), '1'), 12), "")));
+        assert_eq!(parser.parse_result("-012").unwrap_err().offset, 1);
+        assert_eq!(not(item(b'#')).parse(&b"."[..]), Ok(((), &b"."[..])));
+    }
+
+    #[test]
+    fn test_parsers_clone_and_cross_threads() {
+        fn shareable<T: Clone + Send + Sync>(t: T) -> T {
+            t
+        }
+        let entry = shareable(uint32().skip("-").then(uint32()).skip(" ").then(character()).skip(": ").then(word_str()));
+        let copy = entry.clone();
+        let lines = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"];
+        let entry = &entry;
+        let parsed = std::thread::scope(|s| {
+            let workers = lines.iter().map(|line| s.spawn(move || entry.parse_complete(line).unwrap())).collect::<Vec<_>>();
+            workers.into_iter().map(|w| w.join().unwrap()).collect::<Vec<_>>()
+        });
+        assert_eq!(parsed[0], (((1, 3), 'a'), "abcde"));
+        assert_eq!(copy.parse_complete(lines[2]), Ok(parsed[2]));
+        shareable(keywords(["a", "b"]).or(literal(String::from("c"))).repeat_delimited(Many, opt(",")));
+    }
+
+    #[test]
+    fn test_take_until_and_recognize_bytes() {
+        let input = &b"FBFB\nLR"[..];
+        assert_eq!(take_until(item(b'\n')).parse(input), Ok((&b"FBFB"[..], &b"\nLR"[..])));
+        assert_eq!(recognize(item(b'F').then(item(b'B')).repeat(Any)).parse(input), Ok((&b"FBFB"[..], &b"\nLR"[..])));
+        assert_eq!(take_until(item(b'X')).parse_result(input).unwrap_err().offset, 7);
+    }
+
+    #[test]
+    fn test_fold_many_error_before_minimum() {
+        let digits = digit().fold_many(3, 0, |n, d| n * 10 + d.to_digit(10).unwrap());
+        assert_eq!(digits.parse("1234"), Ok((123, "4")));
+        assert_eq!(digits.parse_result("12x").unwrap_err().offset, 2);
+    }
+
+    #[test]
+    fn test_repeat_stops_matching_nothing() {
+        let xs = || literal("x");
+        assert_eq!(opt(xs()).repeat(Any).parse("xxy"), Ok((vec![Some("x"), Some("x")], "y")));
+        assert_eq!(peek(xs()).repeat(Any).parse("xy"), Ok((vec![], "xy")));
+        assert_eq!(take_while(|c| c == 'x').repeat(Any).parse("xxy"), Ok((vec!["xx"], "y")));
+        assert_eq!(not(xs()).count_many(Any).parse("y"), Ok((0, "y")));
+        assert_eq!(opt(xs()).repeat_delimited(Any, ",").parse("x,,x;"), Ok((vec![Some("x"), None, Some("x")], ";")));
+
+        // it still counts towards the minimum, since it did match
+        assert_eq!(opt(xs()).repeat(2..).parse("y"), Ok((vec![None, None], "y")));
+        assert!(opt(xs()).up_until("y").parse("xxz").is_err());
+    }
+
     #[test]
     fn test_day2_parse_chained() {
         let input = "1-3 a: abcdef";
@@ -410,4 +1051,135 @@ mod tests {
         assert_eq!('a', letter);
         assert_eq!("abcdef", password);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_identifier_single_character() {
+        assert_eq!(identifier().parse("x = 1").ok(), Some(("x".to_string(), " = 1")));
+        assert_eq!(identifier().parse("_").ok(), Some(("_".to_string(), "")));
+        assert_eq!(identifier().parse("a1b ").ok(), Some(("a1b".to_string(), " ")));
+        assert!(identifier().parse("1a").is_err());
+    }
+
+    #[test]
+    fn test_error_furthest_failure_wins() {
+        let parser = "ab".then("cd").or("a".then("x"));
+        let err = parser.parse("abxy").unwrap_err();
+        assert_eq!(err.remaining(), 2);
+        assert_eq!(err.expected().collect::<Vec<_>>(), vec![&Expected::literal("cd")]);
+
+        let err = parser.parse("ay").unwrap_err();
+        assert_eq!(err.remaining(), 1);
+        assert_eq!(err.expected().collect::<Vec<_>>(), vec![&Expected::literal("x")]);
+    }
+
+    #[test]
+    fn test_error_furthest_failure_passed_over() {
+        // the repeat stops at "abce", but it's the "cd" that's missing, not the end of the input
+        let pairs = "ab".then("cd").repeat(Any);
+        let err = pairs.by_ref().then(eof()).parse_result("abcdabce").unwrap_err();
+        assert_eq!((err.offset, err.expected), (6, vec![Expected::literal("cd")]));
+        let err = pairs.by_ref().skip(eof()).map(|pairs| pairs.len()).parse_result("abcdabce").unwrap_err();
+        assert_eq!(err.offset, 6);
+        let err = "<".but_really(pairs.by_ref()).skip(">").parse_result("<abcdab>").unwrap_err();
+        assert_eq!((err.offset, err.expected), (7, vec![Expected::literal("cd")]));
+
+        // at the same spot, we'd have taken either
+        let err = pairs.by_ref().then(eof()).parse_result("abcdx").unwrap_err();
+        assert_eq!((err.offset, err.expected), (4, vec![Expected::literal("ab"), Expected::named("end of input")]));
+
+        // and so would an opt, but a name covers everything it passed over at its start
+        let err = opt("-").then(uint32()).parse_result("x").unwrap_err();
+        assert_eq!(err.expected, vec![Expected::literal("-"), Expected::named("unsigned integer")]);
+        let err = opt("-").then(uint32()).named("number").then(eof()).parse_result("x").unwrap_err();
+        assert_eq!(err.expected, vec![Expected::named("number")]);
+    }
+
+    #[test]
+    fn test_error_located_on_later_line() {
+        let input = "1-3 a: abcde\n2-9 c: ccccccccc\n4-\tb: bbbb\n";
+        let line = uint32().skip("-").then(uint32()).skip(whitespace()).then(character()).skip(":").skip(" ").then(word());
+        let err = line.repeat_delimited(3, "\n").parse_result(input).unwrap_err();
+
+        assert_eq!((err.offset, err.line, err.column), (32, 3, 3));
+        assert_eq!(err.found.as_deref(), Some("'\\t'"));
+        assert_eq!(err.to_string(), "\
+error: expected unsigned integer, found '\\t'
+ --> 3:3
+  |
+3 | 4-\tb: bbbb
+  |   ^
+");
+    }
+
+    #[test]
+    fn test_error_at_end_of_input() {
+        let err = "a".then("b").parse_result("a").unwrap_err();
+        assert_eq!(err.found, None);
+        assert_eq!(err.to_string().lines().next(), Some("error: expected \"b\", found end of input"));
+    }
+
+    #[test]
+    fn test_bytes_error_located_on_later_line() {
+        let seat = item(b'F').or(item(b'B')).repeat(7).then(item(b'L').or(item(b'R')).repeat(3));
+        let input = &b"FBFBBFFRLR\nBFFFBBFRRR\nFFFBBBFRXR\n"[..];
+        let err = seat.repeat_delimited(3, item(b'\n')).parse_result(input).unwrap_err();
+
+        assert_eq!((err.offset, err.line, err.column), (30, 3, 9));
+        assert_eq!(err.to_string(), "\
+error: expected 'L' or 'R', found 'X'
+ --> 3:9
+  |
+3 | FFFBBBFRXR
+  |         ^
+");
+    }
+
+    #[test]
+    fn test_parse_token_slice() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Token { Num(u32), Plus }
+        impl SliceItem for Token {
+            fn to_snippet(&self) -> std::borrow::Cow<'_, str> {
+                match self {
+                    Token::Num(n) => n.to_string().into(),
+                    Token::Plus => "+".into(),
+                }
+            }
+            fn describe(&self) -> String {
+                format!("{:?}", self)
+            }
+        }
+
+        let num = || any().filter(|t| matches!(t, Token::Num(_))).named("number");
+        let sum = num().repeat_delimited(Many, item(Token::Plus));
+        let tokens = [Token::Num(1), Token::Plus, Token::Num(2), Token::Plus, Token::Plus];
+        assert_eq!(sum.parse(&tokens[..]), Ok((vec![Token::Num(1), Token::Num(2)], &tokens[3..])));
+
+        let err = sum.then(item(Token::Plus)).then(num()).parse_result(&tokens[..]).unwrap_err();
+        assert_eq!((err.offset, err.found.as_deref()), (4, Some("Plus")));
+        assert_eq!(err.to_string().lines().nth(3), Some("1 | 1+2++"));
+    }
+
+    #[test]
+    fn test_integer_bounds() {
+        let (min, max) = (i128::MIN.to_string(), i128::MAX.to_string());
+        assert_eq!(integer::<i128>().parse_result(&min), Ok(i128::MIN));
+        assert_eq!(integer::<i128>().parse_result(&max), Ok(i128::MAX));
+        let max = u64::MAX.to_string();
+        assert_eq!(integer::<u64>().parse_result(&max), Ok(u64::MAX));
+        assert!(integer::<u64>().parse("18446744073709551616").is_err());
+        assert!(integer::<i16>().parse("-32769").is_err());
+        assert_eq!(integer::<usize>().parse("007"), Ok((7, "")));
+        assert!(integer::<i32>().parse("-").is_err());
+    }
+
+    #[test]
+    fn test_uint32_overflow_and_unicode_digits() {
+        let err = uint32().parse_result("4294967296").unwrap_err();
+        assert_eq!(err.expected, vec![Expected::named("a number that fits in u32")]);
+
+        // arabic-indic three is_numeric, but isn't a digit we can add up
+        assert!(digit().parse("\u{0663}").is_err());
+        assert_eq!(uint32().parse("12\u{0663}"), Ok((12, "\u{0663}")));
+    }
+}
@@ -0,0 +1,723 @@
+/*
+    these are the concrete types behind all the Parser combinator methods, like std's Iterator adapters.
+    every one of them just holds its inner parser(s) by value, so a chain of combinators compiles down to
+    one big nested struct, with no boxing or dynamic dispatch in between.
+
+    some of them carry PhantomData for the result types of their inner parsers. rust insists every type
+    parameter on an impl shows up in the type being implemented (E0207), and in something like Map<P, F>,
+    the T in `P: Parser<T>, F: Fn(T) -> U` otherwise doesn't. it's `fn() -> T` so it doesn't affect
+    Send/Sync or drop checking, since we never actually hold a T. it does get in the way of deriving Clone,
+    which would want T to be Clone too, so those get theirs from `impl_clone!` instead.
+*/
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+use super::*;
+
+// Clone for an adapter whenever the `cloned` parameters are, whatever its other (PhantomData) parameters are
+macro_rules! impl_clone {
+    ($($name:ident<$($param:ident),*> where $($cloned:ident),* { $($field:ident),* })*) => {
+        $(
+            impl<$($param),*> Clone for $name<$($param),*> where $($cloned: Clone),* {
+                fn clone(&self) -> Self {
+                    Self { $($field: self.$field.clone()),* }
+                }
+            }
+        )*
+    };
+}
+
+impl_clone! {
+    Map<P, F, T> where P, F { parser, f, _t }
+    AndThen<P, F, T> where P, F { parser, f, _t }
+    Skip<P, Q, U> where P, Q { first, second, _u }
+    ButReally<P, Q, T> where P, Q { first, second, _t }
+    Recognize<P, T> where P { parser, _t }
+    Not<P, T> where P { parser, _t }
+    RepeatDelimited<P, S, U> where P, S { parser, rep, sep, _u }
+    RepeatCollect<P, T, C> where P { repeat, _tc }
+    FoldMany<P, T, A, F> where P, A, F { repeat, init, f, _t }
+    UpUntil<P, Q, U> where P, Q { parser, other, _u }
+}
+
+// a sequence's own `parse`, which runs its `parse_furthest`, so that if it fails, the error is whichever got
+// further of that failure and everything passed over on the way to it
+fn furthest<'a, T, I>(parse_furthest: impl FnOnce(&mut Option<Error>) -> Result<'a, T, I>) -> Result<'a, T, I> {
+    let mut passed = None;
+    parse_furthest(&mut passed).map_err(|e| e.merge_passed(passed))
+}
+
+pub struct Map<P, F, T> {
+    parser: P,
+    f: F,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, F, T> Map<P, F, T> {
+    pub(super) fn new(parser: P, f: F) -> Self {
+        Self { parser, f, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, F: Fn(T) -> U> Parser<'a, U, I> for Map<P, F, T> {
+    fn parse(&self, input: I) -> Result<'a, U, I> {
+        let (val, rest) = self.parser.parse(input)?;
+        Ok(((self.f)(val), rest))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, U, I> {
+        let (val, rest) = self.parser.parse_furthest(input, passed)?;
+        Ok(((self.f)(val), rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub struct AndThen<P, F, T> {
+    parser: P,
+    f: F,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, F, T> AndThen<P, F, T> {
+    pub(super) fn new(parser: P, f: F) -> Self {
+        Self { parser, f, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, P2: Parser<'a, U, I>, F: Fn(T) -> P2> Parser<'a, U, I> for AndThen<P, F, T> {
+    fn parse(&self, input: I) -> Result<'a, U, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, U, I> {
+        let (val, rest) = self.parser.parse_furthest(input, passed)?;
+        (self.f)(val).parse_furthest(rest, passed)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar().then(Grammar::Opaque)
+    }
+}
+
+pub struct Skip<P, Q, U> {
+    first: P,
+    second: Q,
+    _u: PhantomData<fn() -> U>,
+}
+
+impl<P, Q, U> Skip<P, Q, U> {
+    pub(super) fn new(first: P, second: Q) -> Self {
+        Self { first, second, _u: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, Q: Parser<'a, U, I>> Parser<'a, T, I> for Skip<P, Q, U> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        let (val1, next) = self.first.parse_furthest(input, passed)?;
+        let (_val2, rest) = self.second.parse_furthest(next, passed)?;
+        Ok((val1, rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.first.grammar().then(self.second.grammar())
+    }
+}
+
+pub struct ButReally<P, Q, T> {
+    first: P,
+    second: Q,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, Q, T> ButReally<P, Q, T> {
+    pub(super) fn new(first: P, second: Q) -> Self {
+        Self { first, second, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, Q: Parser<'a, U, I>> Parser<'a, U, I> for ButReally<P, Q, T> {
+    fn parse(&self, input: I) -> Result<'a, U, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, U, I> {
+        let (_val1, next) = self.first.parse_furthest(input, passed)?;
+        let (val2, rest) = self.second.parse_furthest(next, passed)?;
+        Ok((val2, rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.first.grammar().then(self.second.grammar())
+    }
+}
+
+#[derive(Clone)]
+pub struct Then<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<P, Q> Then<P, Q> {
+    pub(super) fn new(first: P, second: Q) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, Q: Parser<'a, U, I>> Parser<'a, (T, U), I> for Then<P, Q> {
+    fn parse(&self, input: I) -> Result<'a, (T, U), I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, (T, U), I> {
+        let (val1, next) = self.first.parse_furthest(input, passed)?;
+        let (val2, rest) = self.second.parse_furthest(next, passed)?;
+        Ok(((val1, val2), rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.first.grammar().then(self.second.grammar())
+    }
+}
+
+#[derive(Clone)]
+pub struct Or<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<P, Q> Or<P, Q> {
+    pub(super) fn new(first: P, second: Q) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>, Q: Parser<'a, T, I>> Parser<'a, T, I> for Or<P, Q> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.first.parse(input).or_else(|e1| self.second.parse(input).map_err(|e2| e1.merge(e2)))
+    }
+
+    // falling back to the second parser passes over the first one's failure
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        match self.first.parse_furthest(input, passed) {
+            Ok(found) => Ok(found),
+            Err(e1) => match self.second.parse_furthest(input, passed) {
+                Ok(found) => {
+                    e1.pass_over(passed);
+                    Ok(found)
+                }
+                Err(e2) => Err(e1.merge(e2)),
+            },
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.first.grammar().or(self.second.grammar())
+    }
+}
+
+#[derive(Clone)]
+pub struct Filter<P, F> {
+    parser: P,
+    f: F,
+}
+
+impl<P, F> Filter<P, F> {
+    pub(super) fn new(parser: P, f: F) -> Self {
+        Self { parser, f }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>, F: Fn(&T) -> bool> Parser<'a, T, I> for Filter<P, F> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        let (val, rest) = self.parser.parse_furthest(input, passed)?;
+        if (self.f)(&val) {
+            Ok((val, rest))
+        } else {
+            // the predicate is just a closure, so there's nothing better to call it than this, short of `named`
+            Err(Error::new(input, Expected::named("value matching filter")))
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+#[derive(Clone)]
+pub struct Spanned<P> {
+    parser: P,
+}
+
+impl<P> Spanned<P> {
+    pub(super) fn new(parser: P) -> Self {
+        Self { parser }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, (T, Span), I> for Spanned<P> {
+    fn parse(&self, input: I) -> Result<'a, (T, Span), I> {
+        let (val, rest) = self.parser.parse(input)?;
+        Ok(((val, Span::between(input, rest)), rest))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, (T, Span), I> {
+        let (val, rest) = self.parser.parse_furthest(input, passed)?;
+        Ok(((val, Span::between(input, rest)), rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+#[derive(Clone)]
+pub struct Named<P> {
+    parser: P,
+    name: &'static str,
+}
+
+impl<P> Named<P> {
+    pub(super) fn new(parser: P, name: &'static str) -> Self {
+        Self { parser, name }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, T, I> for Named<P> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        traced(self.name, input, || self.parser.parse(input).map_err(|e| e.relabel(input, Expected::named(self.name))))
+    }
+
+    // what got passed over inside gets relabeled too, or the name would only cover some of what we expected
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        let relabel = |e: Error| e.relabel(input, Expected::named(self.name));
+        let mut inside = None;
+        let result = traced(self.name, input, || self.parser.parse_furthest(input, &mut inside).map_err(relabel));
+        if let Some(e) = inside {
+            relabel(e).pass_over(passed);
+        }
+        result
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::rule(self.name, self.parser.grammar())
+    }
+}
+
+pub struct Recognize<P, T> {
+    parser: P,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, T> Recognize<P, T> {
+    pub(super) fn new(parser: P) -> Self {
+        Self { parser, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, I, I> for Recognize<P, T> {
+    fn parse(&self, input: I) -> Result<'a, I, I> {
+        let (_, rest) = self.parser.parse(input)?;
+        Ok((input.split_at(input.input_len() - rest.input_len()).0, rest))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, I, I> {
+        let (_, rest) = self.parser.parse_furthest(input, passed)?;
+        Ok((input.split_at(input.input_len() - rest.input_len()).0, rest))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+#[derive(Clone)]
+pub struct Sequence<F> {
+    f: F,
+}
+
+impl<F> Sequence<F> {
+    pub(super) fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<'a, I: Input<'a>, T, F: Fn(I, &mut Option<Error>) -> Result<'a, T, I>> Parser<'a, T, I> for Sequence<F> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        furthest(|passed| (self.f)(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        (self.f)(input, passed)
+    }
+}
+
+#[derive(Clone)]
+pub struct Optional<P> {
+    parser: P,
+}
+
+impl<P> Optional<P> {
+    pub(super) fn new(parser: P) -> Self {
+        Self { parser }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, Option<T>, I> for Optional<P> {
+    fn parse(&self, input: I) -> Result<'a, Option<T>, I> {
+        match self.parser.parse(input) {
+            Ok((val, rest)) => Ok((Some(val), rest)),
+            Err(_) => Ok((None, input)),
+        }
+    }
+
+    // not finding it is passed over, since it might well have been meant to be there
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, Option<T>, I> {
+        match self.parser.parse_furthest(input, passed) {
+            Ok((val, rest)) => Ok((Some(val), rest)),
+            Err(e) => {
+                e.pass_over(passed);
+                Ok((None, input))
+            }
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar().repeat(AtMost(1), Grammar::empty())
+    }
+}
+
+#[derive(Clone)]
+pub struct Peek<P> {
+    parser: P,
+}
+
+impl<P> Peek<P> {
+    pub(super) fn new(parser: P) -> Self {
+        Self { parser }
+    }
+}
+
+// looking ahead doesn't match anything, as far as the grammar is concerned
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, T, I> for Peek<P> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        let (val, _) = self.parser.parse(input)?;
+        Ok((val, input))
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::empty()
+    }
+}
+
+pub struct Not<P, T> {
+    parser: P,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, T> Not<P, T> {
+    pub(super) fn new(parser: P) -> Self {
+        Self { parser, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, (), I> for Not<P, T> {
+    fn parse(&self, input: I) -> Result<'a, (), I> {
+        match self.parser.parse(input) {
+            Ok(_) => Err(Error::unexpected(input)),
+            Err(_) => Ok(((), input)),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::empty()
+    }
+}
+
+pub struct ByRef<'p, P> {
+    parser: &'p P,
+}
+
+impl<'p, P> ByRef<'p, P> {
+    pub(super) fn new(parser: &'p P) -> Self {
+        Self { parser }
+    }
+}
+
+// it's just a reference, so it can be copied whether or not the parser can
+impl<'p, P> Clone for ByRef<'p, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'p, P> Copy for ByRef<'p, P> {}
+
+impl<'a, 'p, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, T, I> for ByRef<'p, P> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.parser.parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parser.parse_furthest(input, passed)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+#[derive(Clone)]
+pub struct Described<P, G> {
+    parser: P,
+    grammar: G,
+}
+
+impl<P, G> Described<P, G> {
+    pub(super) fn new(parser: P, grammar: G) -> Self {
+        Self { parser, grammar }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>, G: Fn() -> Grammar> Parser<'a, T, I> for Described<P, G> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.parser.parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parser.parse_furthest(input, passed)
+    }
+
+    fn grammar(&self) -> Grammar {
+        (self.grammar)()
+    }
+}
+
+#[derive(Clone)]
+pub struct Traced<P> {
+    parser: P,
+    name: &'static str,
+}
+
+impl<P> Traced<P> {
+    pub(super) fn new(parser: P, name: &'static str) -> Self {
+        Self { parser, name }
+    }
+}
+
+impl<'a, I: Input<'a>, T, P: Parser<'a, T, I>> Parser<'a, T, I> for Traced<P> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        traced(self.name, input, || self.parser.parse(input))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        traced(self.name, input, || self.parser.parse_furthest(input, passed))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub struct RepeatDelimited<P, S, U> {
+    parser: P,
+    rep: Repetition,
+    sep: S,
+    _u: PhantomData<fn() -> U>,
+}
+
+impl<P, S, U> RepeatDelimited<P, S, U> {
+    pub(super) fn new(parser: P, rep: Repetition, sep: S) -> Self {
+        Self { parser, rep, sep, _u: PhantomData }
+    }
+}
+
+impl<P, S, U> RepeatDelimited<P, S, U> {
+    // runs the repetition, handing each result to `consume` as it goes. what `consume` makes of them (a
+    // collection, or a fold) is up to it, and the Ok is where the repetition ended, or the error if it
+    // didn't make the minimum. whatever stopped it after that is passed over
+    fn run<'a, I: Input<'a>, T, R>(&self, input: I, passed: &mut Option<Error>, consume: impl FnOnce(&mut Repeated<'_, P, S, U, T, I>) -> R) -> Result<'a, R, I>
+    where P: Parser<'a, T, I>, S: Parser<'a, U, I> {
+        let mut repeated = Repeated { repeat: self, input, count: 0, done: false, error: None, passed, _t: PhantomData };
+        let result = consume(&mut repeated);
+        match repeated.error {
+            Some(e) => Err(e),
+            None => Ok((result, repeated.input)),
+        }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, S: Parser<'a, U, I>> Parser<'a, Vec<T>, I> for RepeatDelimited<P, S, U> {
+    fn parse(&self, input: I) -> Result<'a, Vec<T>, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, Vec<T>, I> {
+        self.run(input, passed, |repeated| repeated.collect())
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar().repeat(self.rep, self.sep.grammar())
+    }
+}
+
+// the results of a repetition, one at a time, parsed as they're asked for
+struct Repeated<'r, P, S, U, T, I> {
+    repeat: &'r RepeatDelimited<P, S, U>,
+    /// the rest of the input after the last result
+    input: I,
+    count: usize,
+    done: bool,
+    /// why we stopped before the minimum, if we did
+    error: Option<Error>,
+    passed: &'r mut Option<Error>,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<'a, 'r, I: Input<'a>, T, U, P: Parser<'a, T, I>, S: Parser<'a, U, I>> Iterator for Repeated<'r, P, S, U, T, I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.done || self.repeat.rep.met_or_exceeded_by(self.count) {
+            return None;
+        }
+        let item = match self.count {
+            0 => self.repeat.parser.parse_furthest(self.input, self.passed),
+            _ => match self.repeat.sep.parse_furthest(self.input, self.passed) {
+                Ok((_, rest)) => self.repeat.parser.parse_furthest(rest, self.passed),
+                Err(e) => Err(e),
+            },
+        };
+        match item {
+            // once we've got the minimum, something that matched nothing would just match the same nothing forever
+            Ok((_, rest)) if rest.input_len() == self.input.input_len() && self.repeat.rep.min() <= self.count => {
+                self.done = true;
+                None
+            }
+            Ok((val, rest)) => {
+                self.input = rest;
+                self.count += 1;
+                Some(val)
+            }
+            // until we hit the minimum, we MUST match. after that, what stopped us is just passed over
+            Err(e) => {
+                self.done = true;
+                if self.count < self.repeat.rep.min() {
+                    self.error = Some(e);
+                } else {
+                    e.pass_over(self.passed);
+                }
+                None
+            }
+        }
+    }
+}
+
+pub struct RepeatCollect<P, T, C> {
+    repeat: RepeatDelimited<P, Succeed, ()>,
+    _tc: PhantomData<fn(T) -> C>,
+}
+
+impl<P, T, C> RepeatCollect<P, T, C> {
+    pub(super) fn new(parser: P, rep: Repetition) -> Self {
+        Self { repeat: RepeatDelimited::new(parser, rep, Succeed), _tc: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, C: FromIterator<T>, P: Parser<'a, T, I>> Parser<'a, C, I> for RepeatCollect<P, T, C> {
+    fn parse(&self, input: I) -> Result<'a, C, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, C, I> {
+        self.repeat.run(input, passed, |repeated| repeated.collect())
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.repeat.grammar()
+    }
+}
+
+/// what `count_many` makes, which is just a fold that adds one each time
+pub type CountMany<P, T> = FoldMany<P, T, usize, fn(usize, T) -> usize>;
+
+pub struct FoldMany<P, T, A, F> {
+    repeat: RepeatDelimited<P, Succeed, ()>,
+    init: A,
+    f: F,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<P, T, A, F> FoldMany<P, T, A, F> {
+    pub(super) fn new(parser: P, rep: Repetition, init: A, f: F) -> Self {
+        Self { repeat: RepeatDelimited::new(parser, rep, Succeed), init, f, _t: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, A: Clone, F: Fn(A, T) -> A, P: Parser<'a, T, I>> Parser<'a, A, I> for FoldMany<P, T, A, F> {
+    fn parse(&self, input: I) -> Result<'a, A, I> {
+        furthest(|passed| self.parse_furthest(input, passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, A, I> {
+        self.repeat.run(input, passed, |repeated| repeated.fold(self.init.clone(), &self.f))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.repeat.grammar()
+    }
+}
+
+pub struct UpUntil<P, Q, U> {
+    parser: P,
+    other: Q,
+    _u: PhantomData<fn() -> U>,
+}
+
+impl<P, Q, U> UpUntil<P, Q, U> {
+    pub(super) fn new(parser: P, other: Q) -> Self {
+        Self { parser, other, _u: PhantomData }
+    }
+}
+
+impl<'a, I: Input<'a>, T, U, P: Parser<'a, T, I>, Q: Parser<'a, U, I>> Parser<'a, Vec<T>, I> for UpUntil<P, Q, U> {
+    fn parse(&self, input: I) -> Result<'a, Vec<T>, I> {
+        let mut input = input;
+        let mut result = Vec::new();
+
+        // implementation strongly influenced by nom::multi::many_till (but different, and way less verbose)
+        loop {
+            // first check the second parser, if we match, we're done
+            let other_err = match self.other.parse(input) {
+                Ok((_val2, _rest)) => return Ok((result, input)),
+                Err(e) => e,
+            };
+            // if we didn't match, run the first parser and collect its result
+            let (val, next) = match self.parser.parse(input) {
+                Ok(found) => found,
+                Err(e) => return Err(other_err.merge(e)),
+            };
+            // if that matched nothing, the other parser is never going to match here either
+            if next.input_len() == input.input_len() {
+                return Err(other_err);
+            }
+            result.push(val);
+            input = next;
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        // the other parser doesn't get consumed, so whatever comes next is what matches it
+        self.parser.grammar().repeat(Any, Grammar::empty())
+    }
+}
@@ -0,0 +1,219 @@
+/*
+    parsing bitstreams, for puzzle inputs that are really binary packets written out as hex (or as ones and
+    zeros, or as any two letters). the text gets turned into bits up front, one bool per bit, and then a
+    slice of those is an Input like any other, so `then`, `repeat`, `map`, `recursive` and the rest all work
+    on it unchanged. that's eight times the memory of packing them, which for puzzle inputs is nothing, and
+    it means a sub-packet is just a subslice.
+
+    offsets in errors are counted in bits, from the start of the stream, and everything is on line 1.
+*/
+use std::borrow::Cow;
+
+use super::*;
+
+/// A stream of bits to parse, most significant first
+pub type Bits<'a> = &'a [bool];
+
+/// bits show up in errors as 0 and 1
+impl SliceItem for bool {
+    fn to_snippet(&self) -> Cow<'_, str> {
+        Cow::Borrowed(if *self { "1" } else { "0" })
+    }
+
+    fn describe(&self) -> String {
+        format!("bit {}", *self as u8)
+    }
+}
+
+/// A Parser for hex text, as bits, four to a digit. Upper and lower case are both fine
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+///
+/// let bits = hex().parse_complete("D2fe").unwrap();
+/// assert_eq!(bits.len(), 16);
+/// assert_eq!(uint(4).repeat(4).parse_result(&bits), Ok(vec![0xd, 0x2, 0xf, 0xe]));
+///
+/// let err = hex().parse_complete("D2FG").unwrap_err();
+/// assert_eq!((err.offset, err.found.as_deref()), (3, Some("\"G\"")));
+/// ```
+pub fn hex<'a>() -> impl Parser<'a, Vec<bool>> + Clone {
+    let digit = character().filter(char::is_ascii_hexdigit).map(|c| c.to_digit(16).unwrap());
+    digit.fold_many(Any, Vec::new(), |mut bits, n| {
+        bits.extend((0..4).rev().map(|i| n >> i & 1 == 1));
+        bits
+    })
+}
+
+/// A Parser for text made of 0s and 1s, as bits
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+/// assert_eq!(binary().parse("0110 "), Ok((vec![false, true, true, false], " ")));
+/// ```
+pub fn binary<'a>() -> impl Parser<'a, Vec<bool>> + Clone {
+    alphabet("0", "1")
+}
+
+/// A Parser for text made of two alphabets of letters, as bits, where `zeros` are all 0 and `ones` are all 1
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+///
+/// // a day5 boarding pass: a 7 bit row, and a 3 bit column
+/// let bits = alphabet("FL", "BR").parse_complete("FBFBBFFRLR").unwrap();
+/// assert_eq!(uint(7).then(uint(3)).parse_result(&bits), Ok((44, 5)));
+/// ```
+pub fn alphabet<'a>(zeros: &'static str, ones: &'static str) -> impl Parser<'a, Vec<bool>> + Clone {
+    let bit = character().filter(move |c| zeros.contains(*c) || ones.contains(*c)).map(move |c| ones.contains(c));
+    bit.repeat_collect(Any)
+}
+
+/// A Parser for an `n` bit unsigned number, most significant bit first. `n` can be up to 64
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+///
+/// let bits = hex().parse_complete("D2FE28").unwrap();
+/// let header = uint(3).then(uint(3));
+/// assert_eq!(header.parse(&bits[..]), Ok(((6, 4), &bits[6..])));
+///
+/// let err = uint(32).parse_result(&bits).unwrap_err();
+/// assert_eq!(err.expected, vec![Expected::named("32 bit number")]);
+/// ```
+pub fn uint<'a>(n: usize) -> impl Parser<'a, u64, Bits<'a>> + Clone {
+    assert!(n <= 64, "a u64 only has room for 64 bits, not {}", n);
+    move |input: Bits<'a>| {
+        if input.len() < n {
+            return Err(Error::new(input, Expected::named(format!("{} bit number", n))));
+        }
+        let (field, rest) = input.split_at(n);
+        Ok((field.iter().fold(0, |value, &bit| value << 1 | bit as u64), rest))
+    }
+}
+
+/// A Parser for a single bit, as true for 1 and false for 0. See `item(true)` for a bit that has to be set
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+/// let bits = binary().parse_complete("10").unwrap();
+/// assert_eq!(flag().repeat(2).parse_result(&bits), Ok(vec![true, false]));
+/// assert!(flag().parse_result(&bits[2..]).is_err());
+/// ```
+pub fn flag<'a>() -> impl Parser<'a, bool, Bits<'a>> + Clone {
+    any().named("flag")
+}
+
+/// A Parser for sub-packets that take up a given number of bits: first a `len_bits` long number, which says
+/// how many bits come after it, then `parser` as many times as it takes to use every one of those bits up.
+/// It's an error if the last one runs over
+/// ```
+/// use lib::parse::*;
+/// use lib::parse::bits::*;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Packet {
+///     Literal { version: u64, value: u64 },
+///     Operator { version: u64, kind: u64, packets: Vec<Packet> },
+/// }
+///
+/// // an operator with two literals in 27 bits, and one with three literals, counted
+/// let by_length = hex().parse_complete("38006F45291200").unwrap();
+/// let by_count = hex().parse_complete("EE00D40C823060").unwrap();
+///
+/// let packet = recursive(|packet| {
+///     // a literal's value comes in groups of 4 bits, each after a flag saying whether there's another one
+///     let value = item(true).but_really(uint(4)).repeat(Any).then(item(false).but_really(uint(4)))
+///         .map(|(more, last)| more.iter().fold(0, |value, group| value << 4 | group) << 4 | last);
+///     let literal = uint(3).skip(uint(3).filter(|kind| *kind == 4)).then(value)
+///         .map(|(version, value)| Packet::Literal { version, value });
+///
+///     let packets = item(false).but_really(length_prefixed(15, packet.clone()))
+///         .or(item(true).but_really(count_prefixed(11, packet)));
+///     let operator = uint(3).then(uint(3)).then(packets)
+///         .map(|((version, kind), packets)| Packet::Operator { version, kind, packets });
+///     literal.or(operator)
+/// });
+///
+/// assert_eq!(packet.parse_result(&by_length), Ok(Packet::Operator { version: 1, kind: 6, packets: vec![
+///     Packet::Literal { version: 6, value: 10 },
+///     Packet::Literal { version: 2, value: 20 },
+/// ]}));
+///
+/// let values = match packet.parse_result(&by_count) {
+///     Ok(Packet::Operator { packets, .. }) => packets.iter().map(|p| match p {
+///         Packet::Literal { value, .. } => *value,
+///         _ => 0,
+///     }).collect::<Vec<_>>(),
+///     _ => vec![],
+/// };
+/// assert_eq!(values, vec![1, 2, 3]);
+/// ```
+pub fn length_prefixed<'a, T>(len_bits: usize, parser: impl Parser<'a, T, Bits<'a>>) -> impl Parser<'a, Vec<T>, Bits<'a>> {
+    let len = uint(len_bits);
+    move |input: Bits<'a>| {
+        let (n, rest) = len.parse(input)?;
+        let n = n as usize;
+        if rest.len() < n {
+            return Err(Error::new(rest, Expected::named(format!("{} bits of sub-packets", n))));
+        }
+        let (mut inside, after) = rest.split_at(n);
+        let mut items = Vec::new();
+        while !inside.is_empty() {
+            // errors inside only know how much was left of the inside, so the bits after it have to be added back
+            let (item, next) = parser.parse(inside).map_err(|e| {
+                let remaining = e.remaining() + after.len();
+                e.moved_to(remaining)
+            })?;
+            // something that matched nothing would match nothing forever
+            if next.len() == inside.len() {
+                return Err(Error::unexpected(input).moved_to(inside.len() + after.len()));
+            }
+            items.push(item);
+            inside = next;
+        }
+        Ok((items, after))
+    }
+}
+
+/// A Parser for a given number of sub-packets: first a `count_bits` long number, which says how many there
+/// are, then `parser` exactly that many times. See `length_prefixed` for an example
+pub fn count_prefixed<'a, T>(count_bits: usize, parser: impl Parser<'a, T, Bits<'a>>) -> impl Parser<'a, Vec<T>, Bits<'a>> {
+    let count = uint(count_bits);
+    move |input: Bits<'a>| {
+        let (n, rest) = count.parse(input)?;
+        parser.by_ref().repeat(Exactly(n as usize)).parse(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_prefixed_errors_located_in_whole_stream() {
+        let good = binary().parse_complete("0100111110").unwrap();
+        let bad = binary().parse_complete("0100111010").unwrap();
+        // 4 bits of length, then two 2-bit numbers that have to be 3s
+        let three = uint(2).filter(|n| *n == 3);
+        let packets = length_prefixed(4, three).then(flag());
+        assert_eq!(packets.parse_result(&good), Ok((vec![3, 3], true)));
+        assert_eq!(packets.parse_result(&bad).unwrap_err().offset, 6);
+
+        // a 3 bit sub-packet runs over the end of 4 bits
+        let bits = binary().parse_complete("010011111").unwrap();
+        let err = length_prefixed(4, uint(3)).parse_result(&bits).unwrap_err();
+        assert_eq!((err.offset, err.expected), (7, vec![Expected::named("3 bit number")]));
+
+        let err = length_prefixed(4, uint(1)).parse_result(&bits[..6]).unwrap_err();
+        assert_eq!((err.offset, err.expected), (4, vec![Expected::named("4 bits of sub-packets")]));
+    }
+
+    #[test]
+    fn test_count_prefixed_and_wide_numbers() {
+        let bits = hex().parse_complete("8FFFFFFFFFFFFFFFF").unwrap();
+        assert_eq!(count_prefixed(2, flag()).parse(&bits[..]).map(|(flags, rest)| (flags.len(), rest.len())), Ok((2, 64)));
+        assert_eq!(uint(64).parse_result(&bits[4..]), Ok(u64::MAX));
+        assert_eq!(uint(0).parse(&bits[..]), Ok((0, &bits[..])));
+    }
+}
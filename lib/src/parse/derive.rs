@@ -0,0 +1,162 @@
+/*
+    the runtime half of #[derive(Parse)]. the macro itself lives in the lib-derive crate, since proc macros
+    have to be in a crate of their own, and all it does is write the same chain of parsers we'd write by hand,
+    asking each field's type for its parser through the Parse trait.
+*/
+pub use lib_derive::Parse;
+
+use super::*;
+
+/// Something with a canonical Parser, which is what `#[derive(Parse)]` uses for each field.
+///
+/// Deriving it takes a format in a `#[parse]` attribute, where each `{field}` is parsed by that field type's
+/// own parser, and everything else has to match exactly. It also derives `FromStr`, so `.parse::<T>()` works
+/// on any string that's nothing but one of these. Tuple fields are referred to by index, like `{0}`, and `{{` and `}}` are literal braces.
+/// ```
+/// use lib::parse::*;
+///
+/// #[derive(Parse, Debug, PartialEq)]
+/// #[parse("{n1}-{n2} {letter}")]
+/// struct Policy { n1: u32, n2: u32, letter: char }
+///
+/// #[derive(Parse, Debug, PartialEq)]
+/// #[parse("{policy}: {password}")]
+/// struct Entry { policy: Policy, password: String }
+///
+/// let entry: Entry = "1-3 a: abcde".parse().unwrap();
+/// assert_eq!(entry, Entry { policy: Policy { n1: 1, n2: 3, letter: 'a' }, password: "abcde".to_string() });
+///
+/// let err = "1-3a: abcde".parse::<Entry>().unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected \" \", found 'a'"));
+///
+/// // each type is a rule of its own in the grammar
+/// assert_eq!(Entry::parser().grammar().to_ebnf(), "\
+/// Entry ::= Policy \": \" word
+/// Policy ::= unsigned_integer \"-\" unsigned_integer \" \" /./
+/// word ::= /\\w+/
+/// unsigned_integer ::= /[0-9]{1,9}/
+/// ");
+/// ```
+///
+/// Enums get a format for each variant, and the first one that matches wins
+/// ```
+/// use lib::parse::*;
+///
+/// #[derive(Parse, Debug, PartialEq)]
+/// enum Instruction {
+///     #[parse("nop {0}")] Nop(i32),
+///     #[parse("acc {0}")] Acc(i32),
+///     #[parse("jmp {offset}")] Jmp { offset: i32 },
+///     #[parse("halt")] Halt,
+/// }
+///
+/// let program = Instruction::parser().repeat_delimited(Many, "\n");
+/// assert_eq!(
+///     program.parse_result("nop +0\nacc -99\njmp 4\nhalt"),
+///     Ok(vec![Instruction::Nop(0), Instruction::Acc(-99), Instruction::Jmp { offset: 4 }, Instruction::Halt]),
+/// );
+/// ```
+pub trait Parse: Sized {
+    fn parser<'a>() -> BoxedParser<'a, Self>;
+}
+
+macro_rules! impl_parse {
+    ($($t:ty => $parser:expr),* $(,)?) => {
+        $(
+            impl Parse for $t {
+                fn parser<'a>() -> BoxedParser<'a, Self> {
+                    $parser.boxed()
+                }
+            }
+        )*
+    };
+}
+
+impl_parse!(
+    u8 => integer::<u8>(), u16 => integer::<u16>(), u32 => uint32(), u64 => integer::<u64>(),
+    u128 => integer::<u128>(), usize => integer::<usize>(),
+    i8 => integer::<i8>(), i16 => integer::<i16>(), i32 => integer::<i32>(), i64 => integer::<i64>(),
+    i128 => integer::<i128>(), isize => integer::<isize>(),
+    f32 => float::<f32>(), f64 => float::<f64>(),
+    char => character(),
+    // a String field is a single word, since we'd have no idea where a more general one stops
+    String => word(),
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Parse, Debug, PartialEq)]
+    #[parse("{{{1}, {0}}}")]
+    struct Flipped(u8, char);
+
+    #[derive(Parse, Debug, PartialEq)]
+    enum Shape {
+        #[parse("square {0}")]
+        Square(Flipped),
+        #[parse("rect {w}x{h}")]
+        Rect { w: f64, h: f64 },
+    }
+
+    #[test]
+    fn test_derive_tuple_fields_and_escaped_braces() {
+        assert_eq!("{x, 7}".parse(), Ok(Flipped(7, 'x')));
+        assert_eq!("square {x, 7}".parse(), Ok(Shape::Square(Flipped(7, 'x'))));
+        assert_eq!("rect 1.5x2".parse(), Ok(Shape::Rect { w: 1.5, h: 2.0 }));
+    }
+
+    #[derive(Parse, Debug, PartialEq)]
+    #[parse("{type}:{name}!")]
+    struct Tagged {
+        r#type: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_derive_raw_identifiers() {
+        assert_eq!("7:x!".parse(), Ok(Tagged { r#type: 7, name: "x".to_string() }));
+    }
+
+    // a number with an optional sign after it, which is passed over when it isn't there
+    #[derive(Debug, PartialEq)]
+    struct Signed(u32, Option<char>);
+
+    impl Parse for Signed {
+        fn parser<'a>() -> BoxedParser<'a, Self> {
+            uint32().then(opt(character().filter(|c| "+-".contains(*c)).named("sign"))).map(|(n, s)| Signed(n, s)).boxed()
+        }
+    }
+
+    #[derive(Parse, Debug, PartialEq)]
+    #[parse("{0};")]
+    struct Inner(Signed);
+
+    #[derive(Parse, Debug, PartialEq)]
+    #[parse("({0}")]
+    struct Outer(Signed);
+
+    #[derive(Parse, Debug, PartialEq)]
+    #[parse("<{0}>")]
+    struct Nested(Outer);
+
+    #[test]
+    fn test_derive_errors_match_then() {
+        // the missing sign is passed over at "x", which is as far as the ";" gets, so either could go there
+        let by_hand = Signed::parser().skip(";");
+        let err = "7x".parse::<Inner>().unwrap_err();
+        assert_eq!(err, by_hand.parse_result("7x").unwrap_err());
+        assert_eq!(err.expected, vec![Expected::literal(";"), Expected::named("sign")]);
+
+        // and a derived type passes over the same things as a field of another one
+        let err = "<(7x".parse::<Nested>().unwrap_err();
+        assert_eq!((err.offset, err.expected), (3, vec![Expected::literal(">"), Expected::named("sign")]));
+    }
+
+    #[test]
+    fn test_derive_enum_reports_every_variant() {
+        let err = "circle 4".parse::<Shape>().unwrap_err();
+        assert_eq!(err.offset, 0);
+        assert_eq!(err.expected, vec![Expected::literal("rect "), Expected::literal("square ")]);
+    }
+}
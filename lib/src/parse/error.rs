@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{Input, LineCol};
+
+/// Something a parser was looking for when it failed
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expected {
+    /// a specific piece of text, like the `"-"` in `literal("-")`
+    Literal(Cow<'static, str>),
+    /// a class of input, described by name, like `digit` or `any character`
+    Named(Cow<'static, str>),
+}
+
+impl Expected {
+    pub fn literal(s: impl Into<String>) -> Self {
+        Expected::Literal(Cow::Owned(s.into()))
+    }
+
+    pub(crate) fn static_literal(s: &'static str) -> Self {
+        Expected::Literal(Cow::Borrowed(s))
+    }
+
+    pub fn named(s: impl Into<Cow<'static, str>>) -> Self {
+        Expected::Named(s.into())
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Literal(s) => write!(f, "{:?}", s),
+            Expected::Named(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// A failure somewhere in the input.
+///
+/// Parsers only ever see the tail of the input they're given, not the whole thing, so we can't know
+/// the absolute offset of a failure while parsing. What we _can_ know is how much input was left
+/// when we gave up, which is enough to decide which of two failures got further. `locate` turns that
+/// back into an offset, line and column once we have the original input in hand again.
+///
+/// Failures happen constantly while parsing, every time an `or` or a `repeat` tries something that
+/// doesn't pan out, and almost all of them are thrown away. Nearly all of them also expect exactly one
+/// thing, so that one lives inline, and the set only gets allocated once failures actually merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    remaining: usize,
+    expected: Option<Expected>,
+    // never contains `expected`, and is only non-empty if `expected` is Some
+    more_expected: BTreeSet<Expected>,
+}
+
+impl Error {
+    /// a failure at the start of `input`, while looking for `expected`
+    pub fn new<'a>(input: impl Input<'a>, expected: Expected) -> Self {
+        let mut error = Self::unexpected(input);
+        error.expected = Some(expected);
+        error
+    }
+
+    /// a failure at the start of `input`, without any idea of what should have been there instead
+    pub fn unexpected<'a>(input: impl Input<'a>) -> Self {
+        Self { remaining: input.input_len(), expected: None, more_expected: BTreeSet::new() }
+    }
+
+    /// how much input was left when this failure happened
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// everything that would have been acceptable here, sorted and deduplicated
+    pub fn expected(&self) -> impl Iterator<Item=&Expected> {
+        self.expected.iter().chain(&self.more_expected).collect::<BTreeSet<_>>().into_iter()
+    }
+
+    /// combines two failures: whichever got further into the input wins, and if they
+    /// failed at the same spot, we were expecting any of the things either one was
+    pub fn merge(mut self, other: Self) -> Self {
+        use std::cmp::Ordering::*;
+        match self.remaining.cmp(&other.remaining) {
+            Less => self,
+            Greater => other,
+            Equal => {
+                for e in other.expected.into_iter().chain(other.more_expected) {
+                    self.insert(e);
+                }
+                self
+            }
+        }
+    }
+
+    /// merges in the failures that were passed over on the way here, if there were any (see `Parser::parse_furthest`)
+    pub(crate) fn merge_passed(self, passed: Option<Error>) -> Self {
+        match passed {
+            Some(passed) => self.merge(passed),
+            None => self,
+        }
+    }
+
+    /// adds this to the failures that were passed over, for when it didn't stop us
+    pub(crate) fn pass_over(self, passed: &mut Option<Error>) {
+        *passed = Some(self.merge_passed(passed.take()));
+    }
+
+    /// replaces what we were expecting with a single description, but only if we failed right
+    /// at the start of `input` - a failure deeper in has a more specific story to tell
+    pub fn relabel<'a>(mut self, input: impl Input<'a>, expected: Expected) -> Self {
+        if self.remaining == input.input_len() {
+            self.expected = Some(expected);
+            self.more_expected.clear();
+        }
+        self
+    }
+
+    /// the same failure, but somewhere else, for when it gets translated from one kind of input to another
+    pub(crate) fn moved_to(mut self, remaining: usize) -> Self {
+        self.remaining = remaining;
+        self
+    }
+
+    fn insert(&mut self, e: Expected) {
+        match &self.expected {
+            None => self.expected = Some(e),
+            Some(first) if *first == e => {}
+            Some(_) => { self.more_expected.insert(e); }
+        }
+    }
+
+    /// works out where this failure happened in `source`, the full text that was originally parsed.
+    /// for other kinds of input, see `Input::locate`
+    pub fn locate(self, source: &str) -> ParseError {
+        let offset = source.len().saturating_sub(self.remaining);
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+
+        self.located(
+            offset,
+            LineCol::at(source, offset),
+            source[offset..].chars().next().map(|c| format!("{:?}", c)),
+            source[line_start..line_end].trim_end_matches('\r').to_string(),
+            source[line_start..offset].to_string(),
+        )
+    }
+
+    /// the guts of `locate`, once the input has worked out all the details
+    pub(crate) fn located(self, offset: usize, at: LineCol, found: Option<String>, source_line: String, source_prefix: String) -> ParseError {
+        ParseError {
+            offset,
+            line: at.line,
+            column: at.column,
+            expected: self.expected().cloned().collect(),
+            found,
+            source_line,
+            source_prefix,
+        }
+    }
+}
+
+/// A failure, located within the original input. This is what `Parser::parse_result` gives back,
+/// and its `Display` renders a snippet of the offending line with a caret under the failure.
+/// ```
+/// use lib::parse::*;
+/// let err = uint32().skip("-").then(uint32()).parse_result("1x3").unwrap_err();
+/// assert_eq!((err.offset, err.line, err.column), (1, 1, 2));
+/// assert_eq!(err.to_string(), "\
+/// error: expected \"-\", found 'x'
+///  --> 1:2
+///   |
+/// 1 | 1x3
+///   |  ^
+/// ");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// offset into the original input, in bytes for text, or items for slices
+    pub offset: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number, in chars for text, or items for slices
+    pub column: usize,
+    /// everything that would have been acceptable here, sorted and deduplicated
+    pub expected: Vec<Expected>,
+    /// what we got instead, as it would be written in the error message, or None if we were at the end of the input
+    pub found: Option<String>,
+    source_line: String,
+    source_prefix: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let found = self.found.as_deref().unwrap_or("end of input");
+        match self.expected.as_slice() {
+            [] => writeln!(f, "error: unexpected {}", found)?,
+            [one] => writeln!(f, "error: expected {}, found {}", one, found)?,
+            [first, second] => writeln!(f, "error: expected {} or {}, found {}", first, second, found)?,
+            [init @ .., last] => {
+                let init = init.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ");
+                writeln!(f, "error: expected one of {}, or {}, found {}", init, last, found)?
+            }
+        }
+
+        // keep tabs as tabs so the caret lines up with the source line, whatever the tab width
+        let pad = self.source_prefix.chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "{}--> {}:{}", gutter, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        writeln!(f, "{} | {}^", gutter, pad)
+    }
+}
+
+impl std::error::Error for ParseError {}
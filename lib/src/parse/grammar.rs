@@ -0,0 +1,399 @@
+/*
+    parsers describing themselves. every combinator knows what it's made of, so a whole parser can be turned
+    into a Grammar: a tree of literals, patterns, sequences, choices, repetitions and named rules. that can be
+    written out as EBNF, to document what a puzzle's input looks like, or sampled to make up random input that
+    the parser should accept, to throw at a solution by the thousand.
+
+    the one thing that can't describe itself is a plain function, since all we know about a closure is that it
+    parses something. those are Opaque, unless they've been given a description with `.describe()`, which is
+    what all the built in parsers like `uint32()` and `word()` do. anything Opaque can still be written out (as
+    `?`), but not sampled.
+
+    a grammar says what text is shaped like, not everything a parser checks. a `.filter()` or an `and_then`
+    doesn't show up, and neither does the way `or` commits to the first alternative that matches, so a sample
+    is only as good as the grammar is complete.
+*/
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use super::*;
+use crate::rng::Rng;
+
+/// What a parser matches, as a tree, made by `Parser::grammar`
+#[derive(Debug, Clone)]
+pub enum Grammar {
+    /// exactly this text
+    Literal(Cow<'static, str>),
+    /// text that this pattern matches, as in `lib::parse::pattern`
+    Pattern(Pattern),
+    /// each of these in turn. an empty sequence matches nothing at all, and always succeeds
+    Sequence(Vec<Grammar>),
+    /// any one of these
+    Choice(Vec<Grammar>),
+    /// `item` however many times `rep` allows, with `sep` between each one
+    Repeat { item: Box<Grammar>, rep: Repetition, sep: Box<Grammar> },
+    /// a named part of the grammar, which gets a rule of its own in EBNF
+    Rule(Cow<'static, str>, Box<Grammar>),
+    /// a `recursive` parser, which `Ref`s with the same id inside it refer back to
+    Recursive(usize, Box<Grammar>),
+    /// the `Recursive` grammar with this id, from inside its own definition
+    Ref(usize),
+    /// something we can't see inside of, like a closure
+    Opaque,
+}
+
+impl Grammar {
+    pub fn literal(text: impl Into<Cow<'static, str>>) -> Self {
+        Grammar::Literal(text.into())
+    }
+
+    /// text matching `source`, which is a pattern as in `lib::parse::pattern`
+    pub fn pattern(source: &str) -> Self {
+        Grammar::Pattern(pattern(source))
+    }
+
+    pub fn rule(name: impl Into<Cow<'static, str>>, grammar: Grammar) -> Self {
+        Grammar::Rule(name.into(), Box::new(grammar))
+    }
+
+    /// matches nothing, and always succeeds
+    pub fn empty() -> Self {
+        Grammar::Sequence(Vec::new())
+    }
+
+    /// `first` then `second`, flattened into one sequence
+    pub fn then(self, second: Grammar) -> Self {
+        match (self, second) {
+            (Grammar::Sequence(mut first), Grammar::Sequence(second)) => {
+                first.extend(second);
+                Grammar::Sequence(first)
+            }
+            (Grammar::Sequence(mut first), second) => {
+                first.push(second);
+                Grammar::Sequence(first)
+            }
+            (first, Grammar::Sequence(mut second)) => {
+                second.insert(0, first);
+                Grammar::Sequence(second)
+            }
+            (first, second) => Grammar::Sequence(vec![first, second]),
+        }
+    }
+
+    /// `first` or `second`, flattened into one choice
+    pub fn or(self, second: Grammar) -> Self {
+        match (self, second) {
+            (Grammar::Choice(mut first), Grammar::Choice(second)) => {
+                first.extend(second);
+                Grammar::Choice(first)
+            }
+            (Grammar::Choice(mut first), second) => {
+                first.push(second);
+                Grammar::Choice(first)
+            }
+            (first, Grammar::Choice(mut second)) => {
+                second.insert(0, first);
+                Grammar::Choice(second)
+            }
+            (first, second) => Grammar::Choice(vec![first, second]),
+        }
+    }
+
+    pub fn repeat(self, rep: Repetition, sep: Grammar) -> Self {
+        Grammar::Repeat { item: Box::new(self), rep, sep: Box::new(sep) }
+    }
+
+    /// the grammar as EBNF, one rule per line, starting with this one. rules are written like `name ::= ...`,
+    /// literals are quoted, patterns are between slashes, and repetitions are `*`, `+`, `?` or `{m,n}`
+    /// ```
+    /// use lib::parse::*;
+    ///
+    /// let policy = uint32().skip("-").then(uint32()).skip(" ").then(character().describe(|| Grammar::pattern("[a-z]")))
+    ///     .named("policy");
+    /// let entry = policy.skip(": ").then(word()).named("entry");
+    /// assert_eq!(entry.grammar().to_ebnf(), "\
+    /// entry ::= policy \": \" word
+    /// policy ::= unsigned_integer \"-\" unsigned_integer \" \" /[a-z]/
+    /// word ::= /\\w+/
+    /// unsigned_integer ::= /[0-9]{1,9}/
+    /// ");
+    /// ```
+    pub fn to_ebnf(&self) -> String {
+        let mut writer = Ebnf::new();
+        let mut out = String::new();
+        let top = writer.expression(self, Precedence::Choice);
+        if !matches!(self, Grammar::Rule(..) | Grammar::Recursive(..)) {
+            let _ = writeln!(out, "start ::= {}", top);
+        }
+        // rules get their bodies written out in the order they were first mentioned, which can add more rules
+        let mut i = 0;
+        while i < writer.rules.len() {
+            let (name, grammar) = writer.rules[i].clone();
+            let body = writer.expression(grammar, Precedence::Choice);
+            let _ = writeln!(out, "{} ::= {}", name, body);
+            i += 1;
+        }
+        out
+    }
+
+    /// some random text that this grammar matches, or None if there's anything Opaque in it. the same seed
+    /// always makes the same text
+    /// ```
+    /// use lib::parse::*;
+    /// use lib::rng::Rng;
+    ///
+    /// fn list<'a>() -> impl Parser<'a, Vec<u32>> {
+    ///     uint32().repeat_delimited(1..=5, ", ").skip(".")
+    /// }
+    /// let mut rng = Rng::new(2020);
+    /// for _ in 0..100 {
+    ///     let text = list().grammar().sample(&mut rng).unwrap();
+    ///     let numbers = list().parse_complete(&text).unwrap();
+    ///     assert!((1..=5).contains(&numbers.len()));
+    /// }
+    ///
+    /// let opaque = |input| uint32().parse(input);
+    /// assert_eq!(opaque.grammar().sample(&mut rng), None);
+    /// ```
+    pub fn sample(&self, rng: &mut Rng) -> Option<String> {
+        let mut sampler = Sampler { rng, recursive: HashMap::new(), depth: 0 };
+        let mut text = String::new();
+        sampler.sample(self, &mut text).then_some(text)
+    }
+
+    // whether this can be sampled without going back into a recursive grammar
+    fn is_finite(&self) -> bool {
+        match self {
+            Grammar::Literal(_) | Grammar::Pattern(_) | Grammar::Opaque => true,
+            Grammar::Sequence(gs) => gs.iter().all(Grammar::is_finite),
+            Grammar::Choice(gs) => gs.iter().any(Grammar::is_finite),
+            Grammar::Repeat { item, rep, sep } => rep.min() == 0 || (item.is_finite() && sep.is_finite()),
+            Grammar::Rule(_, g) | Grammar::Recursive(_, g) => g.is_finite(),
+            Grammar::Ref(_) => false,
+        }
+    }
+}
+
+/// the same as `to_ebnf`
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_ebnf())
+    }
+}
+
+// how tightly an expression binds, to know when it needs parentheses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Choice,
+    Sequence,
+    Repeat,
+}
+
+struct Ebnf<'g> {
+    /// every rule we've come across, in order, which all need writing out
+    rules: Vec<(String, &'g Grammar)>,
+    /// what each rule name we've used stands for, written out on its own, so two different rules can't share a name
+    bodies: HashMap<String, String>,
+    /// what each recursive grammar is called
+    recursive: HashMap<usize, String>,
+}
+
+impl<'g> Ebnf<'g> {
+    fn new() -> Self {
+        Self { rules: Vec::new(), bodies: HashMap::new(), recursive: HashMap::new() }
+    }
+
+    fn expression(&mut self, grammar: &'g Grammar, context: Precedence) -> String {
+        let (text, precedence) = match grammar {
+            Grammar::Literal(text) => (format!("{:?}", text), Precedence::Repeat),
+            Grammar::Pattern(pattern) => (format!("/{}/", pattern.source()), Precedence::Repeat),
+            Grammar::Sequence(gs) if gs.is_empty() => ("\"\"".to_string(), Precedence::Repeat),
+            Grammar::Sequence(gs) => {
+                let parts = gs.iter().map(|g| self.expression(g, Precedence::Sequence)).collect::<Vec<_>>();
+                (parts.join(" "), Precedence::Sequence)
+            }
+            Grammar::Choice(gs) => {
+                let parts = gs.iter().map(|g| self.expression(g, Precedence::Sequence)).collect::<Vec<_>>();
+                (parts.join(" | "), Precedence::Choice)
+            }
+            Grammar::Repeat { rep, .. } if rep.max() == Some(0) => ("\"\"".to_string(), Precedence::Repeat),
+            Grammar::Repeat { item, rep, sep } => {
+                match sep.as_ref() {
+                    Grammar::Sequence(gs) if gs.is_empty() => {
+                        (self.expression(item, Precedence::Repeat) + &suffix(rep.range()), Precedence::Repeat)
+                    }
+                    sep => {
+                        // the first one, then the rest with separators in front
+                        let item = self.expression(item, Precedence::Sequence);
+                        let sep = self.expression(sep, Precedence::Sequence);
+                        let rest = (rep.min().saturating_sub(1), rep.max().map(|max| max - 1));
+                        let text = format!("{} ({} {}){}", item, sep, item, suffix(rest));
+                        if rep.min() == 0 {
+                            (format!("({})?", text), Precedence::Repeat)
+                        } else {
+                            (text, Precedence::Sequence)
+                        }
+                    }
+                }
+            }
+            Grammar::Rule(name, g) => {
+                let body = match g.as_ref() {
+                    Grammar::Recursive(_, inner) => inner.as_ref(),
+                    g => g,
+                };
+                let name = self.name(name, body);
+                if let Grammar::Recursive(id, _) = g.as_ref() {
+                    self.recursive.entry(*id).or_insert_with(|| name.clone());
+                }
+                (name, Precedence::Repeat)
+            }
+            Grammar::Recursive(id, g) => {
+                let name = match self.recursive.get(id) {
+                    Some(name) => name.clone(),
+                    None => self.name("recursive", g),
+                };
+                self.recursive.insert(*id, name.clone());
+                (name, Precedence::Repeat)
+            }
+            Grammar::Ref(id) => (self.recursive.get(id).cloned().unwrap_or_else(|| "recursive".to_string()), Precedence::Repeat),
+            Grammar::Opaque => ("?".to_string(), Precedence::Repeat),
+        };
+        if precedence < context {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+
+    // the name for a rule, whose body gets written out later. rules made by calling the same function twice,
+    // like two `uint32()`s, are the same rule, but a different rule with the same name gets a number on the end
+    fn name(&mut self, name: &str, body: &'g Grammar) -> String {
+        let base = name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+        let text = Ebnf::new().expression(body, Precedence::Choice);
+        let mut n = 1;
+        loop {
+            let name = if n == 1 { base.clone() } else { format!("{}_{}", base, n) };
+            match self.bodies.get(&name) {
+                Some(seen) if *seen == text => return name,
+                Some(_) => n += 1,
+                None => {
+                    self.bodies.insert(name.clone(), text);
+                    self.rules.push((name.clone(), body));
+                    return name;
+                }
+            }
+        }
+    }
+}
+
+// how a repetition looks in EBNF, from its least and most
+fn suffix(range: (usize, Option<usize>)) -> String {
+    match range {
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (0, Some(1)) => "?".to_string(),
+        (min, None) => format!("{{{},}}", min),
+        (min, Some(max)) if min == max => format!("{{{}}}", min),
+        (min, Some(max)) => format!("{{{},{}}}", min, max),
+    }
+}
+
+// how deep into recursive grammars sampling goes before it starts heading for the way out
+const MAX_SAMPLE_DEPTH: usize = 16;
+
+struct Sampler<'r, 'g> {
+    rng: &'r mut Rng,
+    /// the recursive grammars we're inside of, for their refs
+    recursive: HashMap<usize, &'g Grammar>,
+    depth: usize,
+}
+
+impl<'r, 'g> Sampler<'r, 'g> {
+    // adds a sample of `grammar` onto `text`, or says it can't
+    fn sample(&mut self, grammar: &'g Grammar, text: &mut String) -> bool {
+        match grammar {
+            Grammar::Literal(literal) => text.push_str(literal),
+            Grammar::Pattern(pattern) => text.push_str(&pattern.sample(self.rng)),
+            Grammar::Sequence(gs) => return gs.iter().all(|g| self.sample(g, text)),
+            Grammar::Choice(gs) => {
+                // too deep, so only pick from the ones that don't go any deeper, if there are any
+                let finite = gs.iter().filter(|g| g.is_finite()).collect::<Vec<_>>();
+                let pick = if self.depth >= MAX_SAMPLE_DEPTH && !finite.is_empty() {
+                    finite[self.rng.below(finite.len() as u64) as usize]
+                } else {
+                    match self.rng.choose(gs) {
+                        Some(g) => g,
+                        None => return false,
+                    }
+                };
+                return self.sample(pick, text);
+            }
+            Grammar::Repeat { item, rep, sep } => {
+                // a handful more than the minimum, or the minimum if we're too deep already
+                let extra = match rep.max() {
+                    _ if self.depth >= MAX_SAMPLE_DEPTH => 0,
+                    Some(max) => self.rng.between(0, (max - rep.min()).min(MAX_EXTRA_REPEATS) as u64) as usize,
+                    None => self.rng.between(0, MAX_EXTRA_REPEATS as u64) as usize,
+                };
+                for i in 0..rep.min() + extra {
+                    if i > 0 && !self.sample(sep, text) {
+                        return false;
+                    }
+                    if !self.sample(item, text) {
+                        return false;
+                    }
+                }
+            }
+            Grammar::Rule(_, g) => return self.sample(g, text),
+            Grammar::Recursive(id, g) => {
+                self.recursive.insert(*id, g);
+                return self.sample(g, text);
+            }
+            Grammar::Ref(id) => {
+                let Some(&g) = self.recursive.get(id) else { return false };
+                self.depth += 1;
+                let sampled = self.sample(g, text);
+                self.depth -= 1;
+                return sampled;
+            }
+            Grammar::Opaque => return false,
+        }
+        true
+    }
+}
+
+// how many more times than the minimum a repetition goes, at most, when sampling
+const MAX_EXTRA_REPEATS: usize = 4;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list<'a>() -> impl Parser<'a, ()> {
+        recursive(|list| {
+            "[".but_really(list.or(uint32().map(|_| ())).repeat_delimited(Any, ",")).skip("]").map(|_| ())
+        }).named("list")
+    }
+
+    #[test]
+    fn test_grammar_recursive_ebnf_and_sample() {
+        assert_eq!(list().grammar().to_ebnf(), "\
+list ::= \"[\" ((list | unsigned_integer) (\",\" (list | unsigned_integer))*)? \"]\"
+unsigned_integer ::= /[0-9]{1,9}/
+");
+        let mut rng = Rng::new(3);
+        let grammar = list().grammar();
+        let texts = (0..200).map(|_| grammar.sample(&mut rng).unwrap()).collect::<Vec<_>>();
+        let list = list();
+        for text in &texts {
+            assert_eq!(list.parse_complete(text), Ok(()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_grammar_flattens_and_separates() {
+        let parser = "a".or("b").or("c").then(literal("x").repeat(2..=3)).then(literal("y").repeat_delimited(Many, ";"));
+        assert_eq!(parser.grammar().to_ebnf(), "start ::= (\"a\" | \"b\" | \"c\") \"x\"{2,3} \"y\" (\";\" \"y\")*\n");
+    }
+}
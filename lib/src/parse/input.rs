@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+
+use super::*;
+
+/// Something a Parser can consume: text, bytes, or a slice of tokens from a lexer.
+///
+/// An Input is always a cheap view (`&str`, `&[T]`) that parsers split up as they go, handing the `rest`
+/// on to the next parser. Everything is measured in whatever units the input uses for its own length,
+/// so bytes for `&str` (which is why offsets in errors and spans are byte offsets) and items for slices.
+pub trait Input<'a>: Copy {
+    /// what a single step through the input produces: `char` for text, `u8` for bytes, and so on
+    type Item;
+
+    /// how much input is left
+    fn input_len(&self) -> usize;
+
+    /// splits off the first item, or None if there's nothing left
+    fn next_item(self) -> Option<(Self::Item, Self)>;
+
+    /// splits into the first `n` units of input, and the rest
+    fn split_at(self, n: usize) -> (Self, Self);
+
+    /// whether this is nothing but a single line ending
+    fn is_line_ending(&self) -> bool;
+
+    /// a short description of this input, for saying what was left over when nothing should have been
+    fn leftover(self) -> String;
+
+    /// where in memory this input starts. together with `input_len`, that tells apart any two pieces of input
+    /// that are around at the same time, even if they're equal
+    fn address(&self) -> usize;
+
+    /// works out where `error` happened, if this is the whole original input that was parsed
+    fn locate(self, error: Error) -> ParseError;
+}
+
+/// how many chars (or items) of leftover input get shown in an error
+const LEFTOVER_PREVIEW: usize = 24;
+
+impl<'a> Input<'a> for &'a str {
+    type Item = char;
+
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+
+    fn next_item(self) -> Option<(char, Self)> {
+        let c = self.chars().next()?;
+        Some((c, &self[c.len_utf8()..]))
+    }
+
+    fn split_at(self, n: usize) -> (Self, Self) {
+        str::split_at(self, n)
+    }
+
+    fn is_line_ending(&self) -> bool {
+        *self == "\n" || *self == "\r\n"
+    }
+
+    fn leftover(self) -> String {
+        let mut preview = self.chars().take(LEFTOVER_PREVIEW).collect::<String>();
+        if preview.len() < self.len() {
+            preview.push('…');
+        }
+        format!("{:?}", preview)
+    }
+
+    fn address(&self) -> usize {
+        self.as_ptr() as usize
+    }
+
+    fn locate(self, error: Error) -> ParseError {
+        error.locate(self)
+    }
+}
+
+/// Anything that can be parsed out of a slice, like bytes, or tokens from a lexer.
+/// This is mostly about being able to show the input back to people when something goes wrong
+pub trait SliceItem: Copy {
+    /// how this item reads in an error snippet of the surrounding line
+    fn to_snippet(&self) -> Cow<'_, str>;
+
+    /// how this item reads in an error message, as in "found ..."
+    fn describe(&self) -> String;
+
+    /// whether this item ends a line, for working out line numbers in errors
+    fn is_newline(&self) -> bool {
+        false
+    }
+}
+
+/// bytes are shown as the (ASCII) text they most likely are
+impl SliceItem for u8 {
+    fn to_snippet(&self) -> Cow<'_, str> {
+        // tabs stay as tabs, so the caret under the snippet still lines up
+        match self {
+            b'\t' => Cow::Borrowed("\t"),
+            _ => Cow::Owned(self.escape_ascii().to_string()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        if self.is_ascii() {
+            format!("{:?}", *self as char)
+        } else {
+            format!("byte 0x{:02x}", self)
+        }
+    }
+
+    fn is_newline(&self) -> bool {
+        *self == b'\n'
+    }
+}
+
+impl<'a, T: SliceItem> Input<'a> for &'a [T] {
+    type Item = T;
+
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+
+    fn next_item(self) -> Option<(T, Self)> {
+        let (first, rest) = self.split_first()?;
+        Some((*first, rest))
+    }
+
+    fn split_at(self, n: usize) -> (Self, Self) {
+        <[T]>::split_at(self, n)
+    }
+
+    fn is_line_ending(&self) -> bool {
+        matches!(self, [item] if item.is_newline())
+    }
+
+    fn leftover(self) -> String {
+        let mut preview = self.iter().take(LEFTOVER_PREVIEW).map(T::to_snippet).collect::<String>();
+        if self.len() > LEFTOVER_PREVIEW {
+            preview.push('…');
+        }
+        // snippets are already escaped
+        format!("\"{}\"", preview)
+    }
+
+    fn address(&self) -> usize {
+        self.as_ptr() as usize
+    }
+
+    fn locate(self, error: Error) -> ParseError {
+        let offset = self.len().saturating_sub(error.remaining());
+        let line_start = self[..offset].iter().rposition(T::is_newline).map_or(0, |i| i + 1);
+        let line_end = self[offset..].iter().position(T::is_newline).map_or(self.len(), |i| offset + i);
+        let snippet = |items: &[T]| items.iter().map(T::to_snippet).collect::<String>();
+
+        error.located(
+            offset,
+            LineCol {
+                line: self[..offset].iter().filter(|i| i.is_newline()).count() + 1,
+                column: offset - line_start + 1,
+            },
+            self.get(offset).map(T::describe),
+            snippet(&self[line_start..line_end]),
+            snippet(&self[line_start..offset]),
+        )
+    }
+}
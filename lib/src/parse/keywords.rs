@@ -0,0 +1,160 @@
+/*
+    matching one of a fixed set of words, like field names or opcodes. trying each word in turn with `or` is
+    fine for a handful, but it compares the input against every one of them, and whichever comes first wins,
+    so "in" listed before "int" means "int" never matches.
+
+    this builds the words into a trie once, up front, and then walks the input through it one byte at a time,
+    so each byte gets looked at once no matter how many words there are, and the longest word that matches
+    always wins, whatever order they were given in. every word is a str, so a match always ends on a char
+    boundary, even though the trie itself only knows about bytes.
+*/
+use super::*;
+
+/// A Parser for the longest of a fixed set of words, giving back the value that goes with it. Made by
+/// `one_of_map`, or `keywords` for just the words
+#[derive(Clone)]
+pub struct OneOf<V> {
+    nodes: Vec<Node>,
+    values: Vec<V>,
+    /// in the order they were given, for errors and the grammar
+    words: Vec<String>,
+}
+
+#[derive(Default, Clone)]
+struct Node {
+    /// the byte that leads to each child, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// the index of the value for the word that ends here, if one does
+    value: Option<usize>,
+}
+
+/// A Parser for whichever of `pairs`' words is at the start of the input, giving back the value paired with it.
+/// When more than one matches, the longest one wins. A word can't be in there twice
+/// ```
+/// use lib::parse::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Op { In, Int, Inc }
+///
+/// let ops = one_of_map([("in", Op::In), ("int", Op::Int), ("inc", Op::Inc)]);
+/// assert_eq!(ops.parse("int x"), Ok((Op::Int, " x")));
+/// assert_eq!(ops.parse("in x"), Ok((Op::In, " x")));
+/// assert_eq!(ops.parse("ink"), Ok((Op::In, "k")));
+/// assert_eq!(ops.get("inc"), Some(&Op::Inc));
+///
+/// let err = ops.parse_result("out").unwrap_err();
+/// assert_eq!(err.expected, vec![Expected::literal("in"), Expected::literal("inc"), Expected::literal("int")]);
+/// ```
+pub fn one_of_map<K: AsRef<str>, V>(pairs: impl IntoIterator<Item = (K, V)>) -> OneOf<V> {
+    let mut one_of = OneOf { nodes: vec![Node::default()], values: Vec::new(), words: Vec::new() };
+    for (word, value) in pairs {
+        one_of.insert(word.as_ref(), value);
+    }
+    one_of
+}
+
+/// A Parser for whichever of `words` is at the start of the input, borrowed from the input. When more than
+/// one matches, the longest one wins
+/// ```
+/// use lib::parse::*;
+/// let colors = keywords(["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]);
+/// assert_eq!(colors.parse("grn\n"), Ok(("grn", "\n")));
+/// assert!(colors.parse("gr").is_err());
+/// ```
+pub fn keywords<'a, K: AsRef<str>>(words: impl IntoIterator<Item = K>) -> impl Parser<'a, &'a str> + Clone {
+    recognize(one_of_map(words.into_iter().map(|word| (word, ()))))
+}
+
+impl<V> OneOf<V> {
+    fn insert(&mut self, word: &str, value: V) {
+        let mut node = 0;
+        for &byte in word.as_bytes() {
+            node = match self.nodes[node].children.binary_search_by_key(&byte, |&(b, _)| b) {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (byte, child));
+                    child
+                }
+            };
+        }
+        assert!(self.nodes[node].value.is_none(), "{:?} is in there more than once", word);
+        self.nodes[node].value = Some(self.values.len());
+        self.values.push(value);
+        self.words.push(word.to_string());
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        children.binary_search_by_key(&byte, |&(b, _)| b).ok().map(|i| children[i].1)
+    }
+
+    /// the value for exactly `word`, if it's one of ours
+    pub fn get(&self, word: &str) -> Option<&V> {
+        let node = word.bytes().try_fold(0, |node, byte| self.child(node, byte))?;
+        self.nodes[node].value.map(|i| &self.values[i])
+    }
+
+    // the longest word at the start of `input`, as the index of its value and how long it is
+    fn longest(&self, input: &str) -> Option<(usize, usize)> {
+        let mut longest = self.nodes[0].value.map(|i| (i, 0));
+        let mut node = 0;
+        for (len, byte) in input.bytes().enumerate() {
+            match self.child(node, byte) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(i) = self.nodes[node].value {
+                longest = Some((i, len + 1));
+            }
+        }
+        longest
+    }
+}
+
+impl<'a, V: Clone> Parser<'a, V> for OneOf<V> {
+    fn parse(&self, input: &'a str) -> Result<'a, V> {
+        match self.longest(input) {
+            Some((i, len)) => Ok((self.values[i].clone(), &input[len..])),
+            None => Err(self.words.iter()
+                .map(|word| Error::new(input, Expected::literal(word.as_str())))
+                .fold(Error::unexpected(input), Error::merge)),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Choice(self.words.iter().map(|word| Grammar::literal(word.clone())).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_of_longest_match_any_order() {
+        let forwards = keywords(["a", "ab", "abc"]);
+        let backwards = keywords(["abc", "ab", "a"]);
+        for input in ["abcd", "abd", "ad", "xa"] {
+            let expected = |e: Error| e.expected().cloned().collect::<Vec<_>>();
+            assert_eq!(forwards.parse(input).map_err(expected), backwards.parse(input).map_err(expected), "{}", input);
+        }
+        assert_eq!(forwards.parse("abd"), Ok(("ab", "d")));
+
+        // multi-byte characters only ever match whole
+        let arrows = one_of_map([("→", 1), ("→→", 2), ("⇒", 3)]);
+        assert_eq!(arrows.parse("→→→"), Ok((2, "→")));
+        assert!(arrows.parse("←").is_err());
+        assert_eq!(arrows.get("→→"), Some(&2));
+        assert_eq!(arrows.get("→→→"), None);
+    }
+
+    #[test]
+    fn test_one_of_empty_word_always_matches() {
+        let sign = one_of_map([("", 1), ("-", -1), ("+", 1)]);
+        assert_eq!(sign.parse("-5"), Ok((-1, "5")));
+        assert_eq!(sign.parse("5"), Ok((1, "5")));
+        assert!(std::panic::catch_unwind(|| one_of_map([("x", 1), ("x", 2)])).is_err());
+    }
+}
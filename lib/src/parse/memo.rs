@@ -0,0 +1,160 @@
+/*
+    memoizing, for grammars that backtrack a lot. `or` starts its second alternative over from the same place
+    as the first, so when both start with the same thing, that thing gets parsed twice, and when that happens
+    at every level of nesting, it adds up to twice as much work per level.
+
+    a memoized parser remembers how it did at every place in the input it's been tried, keyed by where that
+    place is in memory and how much input is left, so it only ever really runs once per place. clones share
+    what they remember, so the usual way to use it is to memoize something once, and then use clones of it
+    in every alternative that starts with it.
+
+    it doesn't help with left recursion: a parser that calls itself at the same place still hasn't finished
+    the first time, so there's nothing remembered yet.
+
+    what it remembers is behind a lock, so a memoized parser can still be `shared` across threads. the lock is
+    never held while the parser runs, so two threads can both miss at the same place and both run it, but they
+    both come up with the same thing.
+*/
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+/// every remembered result, by where it was tried, along with the failures it passed over on the way
+/// (see `Parser::parse_furthest`)
+type Results<T, I> = HashMap<(usize, usize), (std::result::Result<(T, I), Error>, Option<Error>)>;
+
+/// A Parser that remembers what it did at each place in the input, made by `Parser::memoize`
+pub struct Memoize<P, T, I> {
+    memo: Arc<Memo<P, T, I>>,
+}
+
+struct Memo<P, T, I> {
+    parser: P,
+    results: Mutex<Results<T, I>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<P, T, I> Memoize<P, T, I> {
+    pub(super) fn new(parser: P) -> Self {
+        Self {
+            memo: Arc::new(Memo { parser, results: Mutex::new(HashMap::new()), hits: AtomicUsize::new(0), misses: AtomicUsize::new(0) }),
+        }
+    }
+
+    /// how many times a result came from memory instead of running the parser
+    pub fn hits(&self) -> usize {
+        self.memo.hits.load(Ordering::Relaxed)
+    }
+
+    /// how many times the parser actually had to run
+    pub fn misses(&self) -> usize {
+        self.memo.misses.load(Ordering::Relaxed)
+    }
+
+    /// forgets every remembered result, and resets the counts
+    pub fn clear(&self) {
+        self.memo.results.lock().unwrap().clear();
+        self.memo.hits.store(0, Ordering::Relaxed);
+        self.memo.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+/// clones share their memory, and their counts
+impl<P, T, I> Clone for Memoize<P, T, I> {
+    fn clone(&self) -> Self {
+        Self { memo: self.memo.clone() }
+    }
+}
+
+// the results hold on to the inputs they came from for as long as the parser is around, so nothing can be
+// freed, and something else parsed at the same address, while we still remember it
+impl<'a, I: Input<'a>, T: Clone, P: Parser<'a, T, I>> Parser<'a, T, I> for Memoize<P, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        let (result, passed) = self.remember(input);
+        result.map_err(|e| e.merge_passed(passed))
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        let (result, passed_here) = self.remember(input);
+        if let Some(e) = passed_here {
+            e.pass_over(passed);
+        }
+        result
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.memo.parser.grammar()
+    }
+}
+
+impl<'a, I: Input<'a>, T: Clone, P: Parser<'a, T, I>> Memoize<P, T, I> {
+    // how the parser did at the start of `input`, running it if it's never been tried there
+    fn remember(&self, input: I) -> (std::result::Result<(T, I), Error>, Option<Error>) {
+        let key = (input.address(), input.input_len());
+        if let Some(remembered) = self.memo.results.lock().unwrap().get(&key) {
+            self.memo.hits.fetch_add(1, Ordering::Relaxed);
+            return remembered.clone();
+        }
+        self.memo.misses.fetch_add(1, Ordering::Relaxed);
+        // not locking the results while the parser runs, since it might well end up back here
+        let mut passed = None;
+        let result = self.memo.parser.parse_furthest(input, &mut passed);
+        self.memo.results.lock().unwrap().insert(key, (result.clone(), passed.clone()));
+        (result, passed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memoize_shares_between_clones() {
+        let digit = digit().memoize();
+        let either = digit.clone().skip("a").or(digit.clone().skip("b"));
+        assert_eq!(either.parse("1b"), Ok(('1', "")));
+        assert_eq!((digit.misses(), digit.hits()), (1, 1));
+        assert!(either.parse("xb").is_err());
+        assert_eq!((digit.misses(), digit.hits()), (2, 2));
+
+        digit.clear();
+        assert_eq!((digit.misses(), digit.hits()), (0, 0));
+    }
+
+    #[test]
+    fn test_memoize_keeps_passed_over_errors() {
+        // the repeat stops at "x" wanting another number, which is just as far as the eof gets
+        let items = uint32().skip(",").repeat(Any);
+        let input = "1,2,x";
+        let plain = items.clone().then(eof()).parse_result(input).unwrap_err();
+        assert_eq!((plain.offset, plain.expected.len()), (4, 2));
+
+        let memoized = items.memoize();
+        let list = memoized.clone().then(eof());
+        assert_eq!(list.parse_result(input).unwrap_err(), plain);
+        // and the same again once it's coming from memory
+        assert_eq!(list.parse_result(input).unwrap_err(), plain);
+        assert_eq!((memoized.misses(), memoized.hits()), (1, 1));
+    }
+
+    #[test]
+    fn test_memoize_can_be_shared() {
+        let digit = digit().memoize().shared();
+        let input = "7";
+        let other = digit.clone();
+        assert_eq!(std::thread::scope(|s| s.spawn(|| other.parse(input)).join().unwrap()), Ok(('7', "")));
+        assert_eq!(digit.parse(input), Ok(('7', "")));
+    }
+
+    #[test]
+    fn test_memoize_keyed_by_place_not_text() {
+        let letter = character().memoize();
+        let input = "aa";
+        assert_eq!(letter.parse(input), Ok(('a', "a")));
+        assert_eq!(letter.parse(&input[1..]), Ok(('a', "")));
+        assert_eq!(letter.misses(), 2);
+    }
+}
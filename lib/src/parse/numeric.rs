@@ -0,0 +1,195 @@
+/*
+    numbers, of every shape the puzzles throw at us.
+
+    integers accumulate digit by digit with checked arithmetic, so a number that doesn't fit is a parse error
+    rather than a silent wraparound (or a panic in debug builds). negative numbers accumulate downwards from zero,
+    otherwise i8 could never parse -128, since 128 doesn't fit.
+*/
+use std::str::FromStr;
+
+use super::*;
+
+/// The primitive integer types, which is everything `integer()` and friends can produce
+pub trait Integer: Copy {
+    const SIGNED: bool;
+    const ZERO: Self;
+    /// what we say we expected, when a number is too big (or small) for this type
+    const FITS: &'static str;
+    /// the biggest value of this type, as a u128
+    const MAX: u128;
+
+    /// `self * radix + digit`, or `self * radix - digit` for negative numbers, or None on overflow
+    fn push_digit(self, radix: u32, digit: u32, negative: bool) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty: $signed:expr),*) => {
+        $(
+            impl Integer for $t {
+                const SIGNED: bool = $signed;
+                const ZERO: Self = 0;
+                const FITS: &'static str = concat!("a number that fits in ", stringify!($t));
+                const MAX: u128 = <$t>::MAX as u128;
+
+                fn push_digit(self, radix: u32, digit: u32, negative: bool) -> Option<Self> {
+                    // radix is at most 36, so these casts always fit, even in an i8
+                    let n = self.checked_mul(radix as Self)?;
+                    if negative {
+                        n.checked_sub(digit as Self)
+                    } else {
+                        n.checked_add(digit as Self)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(
+    u8: false, u16: false, u32: false, u64: false, u128: false, usize: false,
+    i8: true, i16: true, i32: true, i64: true, i128: true, isize: true
+);
+
+/// Creates a Parser for a decimal integer of any primitive type. Signed types accept a leading `-` or `+`.
+/// A number that doesn't fit in `T` is an error, not a wraparound
+/// ```
+/// use lib::parse::*;
+/// assert_eq!(integer::<u8>().parse("255,"), Ok((255, ",")));
+/// assert_eq!(integer::<i8>().parse("-128"), Ok((-128, "")));
+/// assert_eq!(integer::<i64>().parse("+42x"), Ok((42, "x")));
+/// assert!(integer::<u32>().parse("-1").is_err());
+///
+/// let err = integer::<u8>().parse_result("256").unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected a number that fits in u8, found '6'"));
+/// ```
+pub fn integer<'a, T: Integer>() -> impl Parser<'a, T> + Clone {
+    integer_radix(10)
+}
+
+/// Creates a Parser for an integer of any primitive type, written in the given radix (from 2 to 36, in either case)
+/// ```
+/// use lib::parse::*;
+/// assert_eq!(integer_radix::<u32>(16).parse("fffd"), Ok((0xfffd, "")));
+/// assert_eq!(integer_radix::<i32>(2).parse("-1012"), Ok((-5, "2")));
+/// assert_eq!(integer_radix::<u8>(16).parse_result("1ff").unwrap_err().expected, vec![Expected::named("a number that fits in u8")]);
+/// assert_eq!(integer_radix::<u8>(8).parse_result("9").unwrap_err().expected, vec![Expected::named("octal integer")]);
+/// assert_eq!(integer_radix::<u32>(36).grammar().to_ebnf(), "start ::= /[0-9a-zA-Z]{1,6}/\n");
+/// ```
+pub fn integer_radix<'a, T: Integer>(radix: u32) -> impl Parser<'a, T> + Clone {
+    assert!((2..=36).contains(&radix), "radix must be between 2 and 36, not {}", radix);
+    let name = match radix {
+        2 => "binary integer",
+        8 => "octal integer",
+        16 => "hexadecimal integer",
+        _ => "integer",
+    };
+
+    let parser = move |input: &'a str| {
+        let (negative, digits) = match input.chars().next() {
+            Some('-') if T::SIGNED => (true, &input[1..]),
+            Some('+') if T::SIGNED => (false, &input[1..]),
+            _ => (false, input),
+        };
+        accumulate(digits, negative, |c| c.to_digit(radix), radix, || Error::new(digits, Expected::named(name)))
+    };
+    parser.describe(move || {
+        // as many digits as always fit, so every number the grammar makes can be parsed back
+        // past 9, the digits carry on as letters, in either case
+        let last = std::char::from_digit(radix - 1, radix).unwrap();
+        let digits = match radix {
+            2..=10 => format!("[0-{}]", last),
+            _ => format!("[0-9a-{}A-{}]", last, last.to_ascii_uppercase()),
+        };
+        let sign = if T::SIGNED { "-?" } else { "" };
+        Grammar::pattern(&format!("{}{}{{1,{}}}", sign, digits, T::MAX.ilog(radix as u128)))
+    })
+}
+
+/// Creates a Parser for a binary number written with a custom alphabet, where any character in `zeros` means 0,
+/// and any in `ones` means 1. Like a boarding pass from day5, where F and L are 0, and B and R are 1
+/// ```
+/// use lib::parse::*;
+/// let seat = binary_with::<usize>("FL", "BR");
+/// assert_eq!(seat.parse("FBFBBFFRLR"), Ok((357, "")));
+/// assert_eq!(seat.parse("BBFFBBFRLL\n"), Ok((820, "\n")));
+///
+/// let err = seat.parse_result("XFBL").unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected one of \"B\", \"F\", \"L\", or \"R\", found 'X'"));
+/// ```
+pub fn binary_with<'a, T: Integer>(zeros: &'static str, ones: &'static str) -> impl Parser<'a, T> + Clone {
+    let parser = move |input: &'a str| {
+        let bit = |c| if zeros.contains(c) { Some(0) } else if ones.contains(c) { Some(1) } else { None };
+        accumulate(input, false, bit, 2, || {
+            zeros.chars().chain(ones.chars())
+                .map(|c| Error::new(input, Expected::literal(c)))
+                .fold(Error::unexpected(input), Error::merge)
+        })
+    };
+    parser.describe(move || {
+        let bits = zeros.chars().chain(ones.chars())
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_string() } else { format!("\\{}", c) })
+            .collect::<String>();
+        Grammar::pattern(&format!("[{}]{{1,{}}}", bits, T::MAX.ilog2()))
+    })
+}
+
+// the common part of all the integer parsers: reading as many digits as we can out of `digits` (which is the
+// part of the input after any sign). overflow is reported at the digit that tipped it over the edge, which
+// is also far enough in that a `.named()` wrapped around us won't paper over it
+fn accumulate<'a, T: Integer>(
+    digits: &'a str,
+    negative: bool,
+    digit: impl Fn(char) -> Option<u32>,
+    radix: u32,
+    no_digits: impl Fn() -> Error,
+) -> Result<'a, T> {
+    let mut n = T::ZERO;
+    let mut consumed = 0;
+    for (i, c) in digits.char_indices() {
+        match digit(c) {
+            Some(d) => n = n.push_digit(radix, d, negative).ok_or_else(|| Error::new(&digits[i..], Expected::named(T::FITS)))?,
+            None => break,
+        }
+        consumed = i + c.len_utf8();
+    }
+    if consumed == 0 {
+        return Err(no_digits())
+    }
+    Ok((n, &digits[consumed..]))
+}
+
+/// The primitive floating point types, which is everything `float()` can produce
+pub trait Float: FromStr {}
+impl Float for f32 {}
+impl Float for f64 {}
+
+/// Creates a Parser for a decimal floating point number, with an optional sign, fractional part and exponent
+/// ```
+/// use lib::parse::*;
+/// assert_eq!(float::<f64>().parse("3.25,"), Ok((3.25, ",")));
+/// assert_eq!(float::<f64>().parse("-.5"), Ok((-0.5, "")));
+/// assert_eq!(float::<f32>().parse("1e3x"), Ok((1000.0, "x")));
+/// assert_eq!(float::<f64>().parse("2.e"), Ok((2.0, "e")));
+/// assert!(float::<f64>().parse("-.").is_err());
+/// ```
+pub fn float<'a, T: Float>() -> impl Parser<'a, T> + Clone {
+    // nothing here needs to be any good at errors, we relabel them all as "number" anyway
+    let mantissa = take_ascii_digits.then(".".but_really(take_ascii_digits).or(|input: &'a str| Ok((0, input))))
+        .filter(|(whole, frac)| whole + frac > 0);
+    let exponent = "e".or("E").then("-".or("+").or("")).then(take_ascii_digits.filter(|n| *n > 0));
+    let number = "-".or("+").or("").then(mantissa).then(opt(exponent));
+
+    let parser = move |input: &'a str| {
+        let (_, rest) = number.parse(input).map_err(|e| e.relabel(input, Expected::named("number")))?;
+        let text = &input[..input.len() - rest.len()];
+        let value = text.parse::<T>().map_err(|_| Error::new(input, Expected::named("number")))?;
+        Ok((value, rest))
+    };
+    parser.describe(|| Grammar::pattern(r"-?\d{1,6}(\.\d{1,6})?"))
+}
+
+// consumes zero or more ascii digits, and says how many there were
+fn take_ascii_digits(input: &str) -> Result<'_, usize> {
+    let n = input.bytes().take_while(u8::is_ascii_digit).count();
+    Ok((n, &input[n..]))
+}
@@ -0,0 +1,585 @@
+/*
+    a little regex engine, for the bits of puzzle input that are way easier to describe as a regex than as
+    a chain of character().filter(...).repeat(...), like "#[0-9a-f]{6}".
+
+    patterns get parsed into a tree, and compiled into a program for a Pike VM, which is the approach from
+    https://swtch.com/~rsc/regexp/regexp2.html. instead of trying one way through the pattern and backing up
+    when it doesn't work out, it steps every possible way through at once, one character at a time. there are
+    only ever as many of those as there are instructions, so matching is linear in the length of the input,
+    no matter how nasty the pattern is. `(a*)*b` against a long run of a's is no slower than `a*b`.
+
+    threads are kept in priority order, so when there's more than one way to match, we get the same one a
+    backtracking engine would have: greedy quantifiers take as much as they can, and alternatives are tried
+    left to right. matches are always anchored at the start of the input, since this is a parser.
+*/
+use super::*;
+use crate::rng::Rng;
+
+/// Creates a Parser that matches a regular expression at the start of the input, and returns the matching text.
+///
+/// This supports the usual regex syntax, minus captures and backreferences:
+/// - `.` is any character but a newline, and `\d`, `\w` and `\s` (and their negations) are ascii digits, word
+///   characters and whitespace
+/// - character classes like `[a-z_]` and `[^,\n]`
+/// - greedy quantifiers `?`, `*`, `+`, `{m}`, `{m,}` and `{m,n}`, and lazy versions that end in an extra `?`
+/// - alternatives with `|`, and groups with `(...)` or `(?:...)`
+/// - `$`, which only matches at the end of the input
+///
+/// It panics if the pattern isn't valid, since that's a bug in the program, not a problem with the input
+/// ```
+/// use lib::parse::*;
+///
+/// let color = pattern("#[0-9a-f]{6}");
+/// assert_eq!(color.parse("#123abc cm"), Ok(("#123abc", " cm")));
+///
+/// let err = color.parse_result("#123abz").unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected text matching `#[0-9a-f]{6}`, found 'z'"));
+///
+/// let height = pattern(r"1([5-8]\d|9[0-3])cm|(59|6\d|7[0-6])in");
+/// assert_eq!(height.parse("183cm"), Ok(("183cm", "")));
+/// assert_eq!(height.parse("76in"), Ok(("76in", "")));
+/// assert!(height.parse("194cm").is_err());
+/// ```
+pub fn pattern(source: &str) -> Pattern {
+    let tree = match (Reader { chars: source.char_indices().collect(), at: 0 }).pattern() {
+        Ok(tree) => tree,
+        Err((offset, problem)) => panic!("invalid pattern `{}`: {} at offset {}", source, problem, offset),
+    };
+
+    let mut program = Program { insts: Vec::new(), classes: Vec::new() };
+    program.compile(&tree);
+    program.insts.push(Inst::Match);
+    assert!(program.insts.len() <= MAX_PROGRAM, "pattern `{}` is too big, try smaller {{m,n}} counts", source);
+
+    Pattern { source: source.to_string(), program }
+}
+
+/// A compiled regular expression, made by `pattern`
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    program: Program,
+}
+
+impl Pattern {
+    /// the pattern this was compiled from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// whether all of `text` matches this pattern. `parse` takes the first way through the pattern that matches,
+    /// which might not be all of it, but this tries every way through, so `a|ab` matches all of "ab"
+    /// ```
+    /// use lib::parse::*;
+    /// let unit = pattern("in|inch");
+    /// assert_eq!(unit.parse("inch"), Ok(("in", "ch")));
+    /// assert!(unit.matches_all("inch"));
+    /// assert!(!unit.matches_all("inches"));
+    /// ```
+    pub fn matches_all(&self, text: &str) -> bool {
+        self.program.run(text, true).is_ok()
+    }
+
+    /// some random text that this pattern matches. any character is printable ASCII unless the pattern
+    /// says otherwise, and every `*`, `+` or `?` is as likely to stop as to carry on each time around
+    /// ```
+    /// use lib::parse::*;
+    /// use lib::rng::Rng;
+    ///
+    /// let hair = pattern("#[0-9a-f]{6}");
+    /// let mut rng = Rng::new(1);
+    /// for _ in 0..100 {
+    ///     let sample = hair.sample(&mut rng);
+    ///     assert_eq!(hair.parse(&sample), Ok((sample.as_str(), "")));
+    /// }
+    /// ```
+    pub fn sample(&self, rng: &mut Rng) -> String {
+        let mut text = String::new();
+        let mut pc = 0;
+        loop {
+            match self.program.insts[pc] {
+                Inst::Char(c) => text.push(c),
+                Inst::Class(i) => text.push(self.program.classes[i].sample(rng)),
+                Inst::Split(first, second) => {
+                    pc = if rng.chance(0.5) { first } else { second };
+                    continue;
+                }
+                Inst::Jump(to) => {
+                    pc = to;
+                    continue;
+                }
+                Inst::End => {}
+                Inst::Match => return text,
+            }
+            pc += 1;
+        }
+    }
+}
+
+impl<'a> Parser<'a, &'a str> for Pattern {
+    fn parse(&self, input: &'a str) -> Result<'a, &'a str> {
+        match self.program.run(input, false) {
+            Ok(end) => Ok(input.split_at(end)),
+            Err(furthest) => Err(Error::new(&input[furthest..], Expected::named(format!("text matching `{}`", self.source)))),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Pattern(self.clone())
+    }
+}
+
+// counted repetition copies its body over and over, so `(x{1000}){1000}` would blow up without a limit
+const MAX_PROGRAM: usize = 100_000;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Class(Class),
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32>, greedy: bool },
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn of(ranges: &[(char, char)], negated: bool) -> Self {
+        Self { ranges: ranges.to_vec(), negated }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != self.negated
+    }
+
+    // a random character in the class, with every one equally likely, or a printable ASCII one if it's negated
+    fn sample(&self, rng: &mut Rng) -> char {
+        if self.negated {
+            let printable = (' '..='~').filter(|&c| self.matches(c)).collect::<Vec<_>>();
+            return rng.choose(&printable).copied()
+                .or_else(|| (char::MIN..=char::MAX).find(|&c| self.matches(c)))
+                .expect("a negated class matches something");
+        }
+        let size = |&(lo, hi): &(char, char)| hi as u64 - lo as u64 + 1;
+        let mut n = rng.below(self.ranges.iter().map(size).sum());
+        for range in &self.ranges {
+            if n < size(range) {
+                // ranges are made out of chars, so they can only span the surrogate gap, not start or end in it
+                return char::from_u32(range.0 as u32 + n as u32).unwrap_or(range.0);
+            }
+            n -= size(range);
+        }
+        unreachable!("the pick is less than the total size of the ranges")
+    }
+}
+
+const DIGITS: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+// a recursive descent parser for patterns. problems come back as an offset into the pattern and what's wrong
+// there, which `pattern` turns into a panic, since a bad pattern is a bug rather than bad input
+struct Reader {
+    chars: Vec<(usize, char)>,
+    at: usize,
+}
+
+type Problem = (usize, &'static str);
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).map(|&(_, c)| c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars.get(self.at).map_or_else(|| self.chars.last().map_or(0, |&(i, c)| i + c.len_utf8()), |&(i, _)| i)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> std::result::Result<char, Problem> {
+        let c = self.peek().ok_or((self.offset(), "unexpected end of pattern"))?;
+        self.at += 1;
+        Ok(c)
+    }
+
+    fn pattern(&mut self) -> std::result::Result<Node, Problem> {
+        let node = self.alternate()?;
+        match self.peek() {
+            None => Ok(node),
+            Some(_) => Err((self.offset(), "unmatched `)`")),
+        }
+    }
+
+    fn alternate(&mut self) -> std::result::Result<Node, Problem> {
+        let mut alternatives = vec![self.concat()?];
+        while self.eat('|') {
+            alternatives.push(self.concat()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.pop().unwrap() } else { Node::Alternate(alternatives) })
+    }
+
+    fn concat(&mut self) -> std::result::Result<Node, Problem> {
+        let mut nodes = Vec::new();
+        while !matches!(self.peek(), None | Some('|') | Some(')')) {
+            let atom = self.atom()?;
+            nodes.push(self.quantifiers(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn atom(&mut self) -> std::result::Result<Node, Problem> {
+        let offset = self.offset();
+        Ok(match self.next()? {
+            '(' => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err((offset, "the only kind of special group is `(?:...)`"));
+                }
+                let node = self.alternate()?;
+                if !self.eat(')') {
+                    return Err((offset, "unclosed `(`"));
+                }
+                node
+            }
+            '[' => Node::Class(self.class(offset)?),
+            '.' => Node::Class(Class::of(&[('\n', '\n')], true)),
+            '$' => Node::End,
+            '\\' => self.escape(offset)?,
+            '*' | '+' | '?' | '{' => return Err((offset, "nothing to repeat")),
+            c => Node::Char(c),
+        })
+    }
+
+    // everything after the `\` in an escape
+    fn escape(&mut self, offset: usize) -> std::result::Result<Node, Problem> {
+        Ok(match self.next()? {
+            'd' => Node::Class(Class::of(DIGITS, false)),
+            'D' => Node::Class(Class::of(DIGITS, true)),
+            'w' => Node::Class(Class::of(WORD, false)),
+            'W' => Node::Class(Class::of(WORD, true)),
+            's' => Node::Class(Class::of(SPACE, false)),
+            'S' => Node::Class(Class::of(SPACE, true)),
+            'n' => Node::Char('\n'),
+            'r' => Node::Char('\r'),
+            't' => Node::Char('\t'),
+            c if c.is_ascii_alphanumeric() => return Err((offset, "unknown escape")),
+            c => Node::Char(c),
+        })
+    }
+
+    // everything after the `[` in a class
+    fn class(&mut self, offset: usize) -> std::result::Result<Class, Problem> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let item_offset = self.offset();
+            let lo = match self.next().map_err(|_| (offset, "unclosed `[`"))? {
+                ']' if !first => break,
+                '\\' => match self.escape(item_offset)? {
+                    Node::Char(c) => c,
+                    Node::Class(Class { ranges: more, negated: false }) => {
+                        ranges.extend(more);
+                        first = false;
+                        continue;
+                    }
+                    _ => return Err((item_offset, "negated classes like `\\D` can't go inside `[...]`")),
+                },
+                c => c,
+            };
+            first = false;
+
+            // a `-` is a range, unless there's nothing after it to be the end of one
+            let hi = if self.peek() == Some('-') && !matches!(self.chars.get(self.at + 1), Some((_, ']')) | None) {
+                self.at += 1;
+                match self.next()? {
+                    '\\' => match self.escape(item_offset)? {
+                        Node::Char(c) => c,
+                        _ => return Err((item_offset, "a range can only end in a single character")),
+                    },
+                    c => c,
+                }
+            } else {
+                lo
+            };
+            if hi < lo {
+                return Err((item_offset, "range is backwards"));
+            }
+            ranges.push((lo, hi));
+        }
+        Ok(Class { ranges, negated })
+    }
+
+    // any number of quantifiers after `node`, like the `{2,3}` in `a{2,3}`, or the `*?` in `a*?`
+    fn quantifiers(&mut self, mut node: Node) -> std::result::Result<Node, Problem> {
+        loop {
+            let offset = self.offset();
+            let (min, max) = if self.eat('*') {
+                (0, None)
+            } else if self.eat('+') {
+                (1, None)
+            } else if self.eat('?') {
+                (0, Some(1))
+            } else if self.eat('{') {
+                let min = self.number().ok_or((offset, "expected a count after `{`"))?;
+                let max = if self.eat(',') { self.number() } else { Some(min) };
+                if !self.eat('}') {
+                    return Err((offset, "unclosed `{`"));
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err((offset, "repetition count range is backwards"));
+                }
+                (min, max)
+            } else {
+                return Ok(node);
+            };
+            let greedy = !self.eat('?');
+            node = Node::Repeat { node: Box::new(node), min, max, greedy };
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.at;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().map(|&(_, c)| c).collect::<String>().parse().ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Class(usize),
+    /// carry on at both, preferring the first
+    Split(usize, usize),
+    Jump(usize),
+    End,
+    Match,
+}
+
+#[derive(Debug, Clone)]
+struct Program {
+    insts: Vec<Inst>,
+    classes: Vec<Class>,
+}
+
+impl Program {
+    fn compile(&mut self, node: &Node) {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => self.insts.push(Inst::Char(*c)),
+            Node::Class(class) => {
+                self.insts.push(Inst::Class(self.classes.len()));
+                self.classes.push(class.clone());
+            }
+            Node::End => self.insts.push(Inst::End),
+            Node::Concat(nodes) => nodes.iter().for_each(|n| self.compile(n)),
+            Node::Alternate(nodes) => {
+                // split to this one or the rest, over and over, with every one jumping to the end when it's done
+                let mut jumps = Vec::new();
+                for (i, n) in nodes.iter().enumerate() {
+                    if i + 1 < nodes.len() {
+                        let split = self.placeholder();
+                        self.compile(n);
+                        jumps.push(self.placeholder());
+                        self.insts[split] = Inst::Split(split + 1, self.insts.len());
+                    } else {
+                        self.compile(n);
+                    }
+                }
+                for jump in jumps {
+                    self.insts[jump] = Inst::Jump(self.insts.len());
+                }
+            }
+            Node::Repeat { node, min, max, greedy } => {
+                for _ in 0..*min {
+                    self.compile(node);
+                    if self.insts.len() > MAX_PROGRAM {
+                        return;
+                    }
+                }
+                match max {
+                    None => {
+                        let split = self.placeholder();
+                        self.compile(node);
+                        self.insts.push(Inst::Jump(split));
+                        self.insts[split] = self.split(split + 1, self.insts.len(), *greedy);
+                    }
+                    Some(max) => {
+                        // each optional copy can bail out straight to the end
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.placeholder());
+                            self.compile(node);
+                            if self.insts.len() > MAX_PROGRAM {
+                                return;
+                            }
+                        }
+                        let end = self.insts.len();
+                        for split in splits {
+                            self.insts[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn placeholder(&mut self) -> usize {
+        self.insts.push(Inst::Match);
+        self.insts.len() - 1
+    }
+
+    fn split(&self, body: usize, skip: usize, greedy: bool) -> Inst {
+        if greedy { Inst::Split(body, skip) } else { Inst::Split(skip, body) }
+    }
+
+    // how much of `input` matches, or how far we got before every thread died. if it has to be `whole`, only
+    // a match at the end counts, and threads that match any sooner just die
+    fn run(&self, input: &str, whole: bool) -> std::result::Result<usize, usize> {
+        let mut current = Threads::new(self.insts.len());
+        let mut next = Threads::new(self.insts.len());
+        let mut stack = Vec::new();
+        self.add(&mut current, &mut stack, 0, input.is_empty());
+
+        let mut matched = None;
+        let mut furthest = 0;
+        let mut chars = input.char_indices().peekable();
+        loop {
+            let (pos, c) = match chars.next() {
+                Some((pos, c)) => (pos, Some(c)),
+                None => (input.len(), None),
+            };
+            if current.is_empty() {
+                break;
+            }
+            furthest = pos;
+            let next_pos = chars.peek().map_or(input.len(), |&(i, _)| i);
+
+            for &pc in &current.dense {
+                let step = match (&self.insts[pc], c) {
+                    (Inst::Match, Some(_)) if whole => false,
+                    (Inst::Match, _) => {
+                        // anything after this in the list is lower priority, so it can't win any more
+                        matched = Some(pos);
+                        break;
+                    }
+                    (Inst::Char(want), Some(c)) => *want == c,
+                    (Inst::Class(class), Some(c)) => self.classes[*class].matches(c),
+                    _ => false,
+                };
+                if step {
+                    self.add(&mut next, &mut stack, pc + 1, next_pos == input.len());
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+            if c.is_none() {
+                break;
+            }
+        }
+        matched.ok_or(furthest)
+    }
+
+    // adds the thread at `pc` to `threads`, following any jumps and splits along the way, in priority order
+    fn add(&self, threads: &mut Threads, stack: &mut Vec<usize>, pc: usize, at_end: bool) {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if !threads.insert(pc) {
+                continue;
+            }
+            match self.insts[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::End if at_end => stack.push(pc + 1),
+                _ => {}
+            }
+        }
+    }
+}
+
+// an ordered set of instruction indices, that can be cleared in constant time. see
+// https://research.swtch.com/sparse for how it works
+struct Threads {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+}
+
+impl Threads {
+    fn new(size: usize) -> Self {
+        Self { dense: Vec::with_capacity(size), sparse: vec![0; size] }
+    }
+
+    fn insert(&mut self, pc: usize) -> bool {
+        let i = self.sparse[pc];
+        if i < self.dense.len() && self.dense[i] == pc {
+            return false;
+        }
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+        true
+    }
+
+    fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_quantifiers_and_priority() {
+        assert_eq!(pattern(r"\d{9}").parse("0123456789"), Ok(("012345678", "9")));
+        assert!(pattern(r"\d{9}").parse("01234567").is_err());
+        assert_eq!(pattern("a{2,}b?").parse("aaab!"), Ok(("aaab", "!")));
+        assert_eq!(pattern("<.*>").parse("<a><b>c"), Ok(("<a><b>", "c")));
+        assert_eq!(pattern("<.*?>").parse("<a><b>c"), Ok(("<a>", "<b>c")));
+        assert_eq!(pattern("a|ab").parse("abc"), Ok(("a", "bc")));
+        assert_eq!(pattern("(?:ab)+$").parse("ababab"), Ok(("ababab", "")));
+        assert!(pattern("(?:ab)+$").parse("ababa").is_err());
+        assert_eq!(pattern("[^-a-c\\]]*").parse("xyz]"), Ok(("xyz", "]")));
+        assert_eq!(pattern("").parse("abc"), Ok(("", "abc")));
+    }
+
+    #[test]
+    fn test_pattern_no_pathological_backtracking() {
+        // this takes forever with a backtracking engine, since there are 2^n ways to split up the a's
+        let input = "a".repeat(10_000);
+        assert!(pattern("(a*)*b").parse(&input).is_err());
+        assert_eq!(pattern("(a|aa)*$").parse(&input), Ok((input.as_str(), "")));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid pattern `a(b`: unclosed `(` at offset 1")]
+    fn test_pattern_invalid() {
+        pattern("a(b");
+    }
+}
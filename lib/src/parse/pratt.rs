@@ -0,0 +1,242 @@
+/*
+    operator precedence parsing, the Pratt way. https://matklad.github.io/2020/04/13/simple-but-powerful-pratt-parsing.html
+    is the best explanation of this I've found, and this is pretty much that, just driven by a table
+    of operator parsers instead of a match on tokens.
+
+    each precedence level p turns into a pair of binding powers: (2p, 2p+1) for left associative operators,
+    and (2p+1, 2p) for right associative ones. an operator only gets to grab the expression to its left if
+    its left binding power is at least what the caller asked for, which is what makes `1 + 2 * 3` come out
+    as `1 + (2 * 3)` and `1 - 2 - 3` as `(1 - 2) - 3`.
+*/
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+}
+
+/// One operator being applied to its operand(s), which have already been folded into values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation<O, T> {
+    Prefix(O, T),
+    Infix(T, O, T),
+    Postfix(T, O),
+}
+
+/// An expression tree, as built by `Operators::tree`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<A, O> {
+    Atom(A),
+    Prefix(O, Box<Expr<A, O>>),
+    Infix(Box<Expr<A, O>>, O, Box<Expr<A, O>>),
+    Postfix(Box<Expr<A, O>>, O),
+}
+
+/// A table of prefix, infix and postfix operators, to build an expression parser out of.
+///
+/// Every operator is a parser, and they all have to produce the same type `O`, which is what gets
+/// handed to the fold function (or put in the tree) to say which operator was applied. Precedence
+/// levels are any number, and higher numbers bind tighter.
+/// ```
+/// use lib::parse::*;
+///
+/// let eval = |ops: Operators<'static, &'static str>| ops.fold(integer::<i64>(), |op| match op {
+///     Operation::Infix(l, " + ", r) => l + r,
+///     Operation::Infix(l, " * ", r) => l * r,
+///     Operation::Prefix("-", v) => -v,
+///     _ => unreachable!(),
+/// });
+///
+/// // normal maths, where * binds tighter than +
+/// let normal = eval(Operators::new().infix(" + ", 1, Assoc::Left).infix(" * ", 2, Assoc::Left).prefix("-", 3));
+/// assert_eq!(normal.parse_result("1 + 2 * 3 + -4 * 5"), Ok(1 + 2 * 3 + -4 * 5));
+///
+/// // the same table, except now + binds tighter than *
+/// let backwards = eval(Operators::new().infix(" + ", 2, Assoc::Left).infix(" * ", 1, Assoc::Left).prefix("-", 3));
+/// assert_eq!(backwards.parse_result("1 + 2 * 3 + -4 * 5"), Ok((1 + 2) * (3 + -4) * 5));
+/// ```
+pub struct Operators<'a, O, I = &'a str> {
+    prefix: Vec<(BoxedParser<'a, O, I>, u32)>,
+    infix: Vec<(BoxedParser<'a, O, I>, (u32, u32))>,
+    postfix: Vec<(BoxedParser<'a, O, I>, u32)>,
+}
+
+impl<'a, O: 'a, I: Input<'a> + 'a> Operators<'a, O, I> {
+    pub fn new() -> Self {
+        Self { prefix: Vec::new(), infix: Vec::new(), postfix: Vec::new() }
+    }
+
+    /// adds an operator that comes before its operand, like `-x`
+    pub fn prefix(mut self, op: impl Parser<'a, O, I> + 'a, precedence: u32) -> Self {
+        self.prefix.push((op.boxed(), precedence * 2 + 1));
+        self
+    }
+
+    /// adds an operator that goes between its operands, like `x + y`
+    pub fn infix(mut self, op: impl Parser<'a, O, I> + 'a, precedence: u32, assoc: Assoc) -> Self {
+        let (left, right) = match assoc {
+            Assoc::Left => (precedence * 2, precedence * 2 + 1),
+            Assoc::Right => (precedence * 2 + 1, precedence * 2),
+        };
+        self.infix.push((op.boxed(), (left, right)));
+        self
+    }
+
+    /// adds an operator that comes after its operand, like `x!`
+    pub fn postfix(mut self, op: impl Parser<'a, O, I> + 'a, precedence: u32) -> Self {
+        self.postfix.push((op.boxed(), precedence * 2));
+        self
+    }
+
+    /// builds a parser for expressions of `atom`s and these operators, which folds each operation
+    /// into a value with `f` as soon as it's parsed
+    pub fn fold<T: 'a>(self, atom: impl Parser<'a, T, I> + 'a, f: impl Fn(Operation<O, T>) -> T + 'a) -> BoxedParser<'a, T, I> {
+        let pratt = Pratt { ops: self, atom, f };
+        (move |input: I| pratt.expr(input, 0)).boxed()
+    }
+
+    /// builds a parser for expressions of `atom`s and these operators, which produces the whole expression tree
+    /// ```
+    /// use lib::parse::*;
+    /// use Expr::*;
+    ///
+    /// let ops = Operators::new()
+    ///     .infix("+", 1, Assoc::Left)
+    ///     .infix("^", 2, Assoc::Right)
+    ///     .prefix("-", 3)
+    ///     .postfix("!", 4);
+    /// let tree = ops.tree(uint32()).parse_result("-1!+2^3^4").unwrap();
+    ///
+    /// let atom = |n| Box::new(Atom(n));
+    /// assert_eq!(tree, Infix(
+    ///     Box::new(Prefix("-", Box::new(Postfix(atom(1), "!")))),
+    ///     "+",
+    ///     Box::new(Infix(atom(2), "^", Box::new(Infix(atom(3), "^", atom(4))))),
+    /// ));
+    /// ```
+    pub fn tree<A: 'a>(self, atom: impl Parser<'a, A, I> + 'a) -> BoxedParser<'a, Expr<A, O>, I> {
+        self.fold(atom.map(Expr::Atom), |op| match op {
+            Operation::Prefix(op, e) => Expr::Prefix(op, Box::new(e)),
+            Operation::Infix(l, op, r) => Expr::Infix(Box::new(l), op, Box::new(r)),
+            Operation::Postfix(e, op) => Expr::Postfix(Box::new(e), op),
+        })
+    }
+}
+
+impl<'a, O: 'a, I: Input<'a> + 'a> Default for Operators<'a, O, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Pratt<'a, O, I, A, F> {
+    ops: Operators<'a, O, I>,
+    atom: A,
+    f: F,
+}
+
+// whichever of `ops` matches the most input, with its binding power(s), so that `<=` isn't read as `<` followed by
+// `=` just because `<` was added first. on a tie, the one added first wins
+fn longest_match<'a, O, I: Input<'a>, B: Copy>(ops: &[(BoxedParser<'a, O, I>, B)], input: I) -> std::result::Result<(O, B, I), Error> {
+    let mut err = Error::unexpected(input);
+    let mut longest: Option<(O, B, I)> = None;
+    for (op, bp) in ops {
+        match op.parse(input) {
+            Ok((op, rest)) => {
+                if longest.as_ref().is_none_or(|(_, _, best)| rest.input_len() < best.input_len()) {
+                    longest = Some((op, *bp, rest));
+                }
+            }
+            Err(e) => err = err.merge(e),
+        }
+    }
+    longest.ok_or(err)
+}
+
+impl<'a, O, I: Input<'a>, A, F> Pratt<'a, O, I, A, F> {
+    fn expr<T>(&self, input: I, min_bp: u32) -> Result<'a, T, I> where A: Parser<'a, T, I>, F: Fn(Operation<O, T>) -> T {
+        let (mut lhs, mut input) = match longest_match(&self.ops.prefix, input) {
+            Ok((op, bp, rest)) => {
+                let (operand, rest) = self.expr(rest, bp)?;
+                ((self.f)(Operation::Prefix(op, operand)), rest)
+            }
+            Err(prefix_err) => self.atom.parse(input).map_err(|e| prefix_err.merge(e))?,
+        };
+
+        loop {
+            if let Ok((op, bp, rest)) = longest_match(&self.ops.postfix, input) {
+                if bp >= min_bp {
+                    lhs = (self.f)(Operation::Postfix(lhs, op));
+                    input = rest;
+                    continue;
+                }
+            }
+
+            match longest_match(&self.ops.infix, input) {
+                Ok((op, (left, right), rest)) if left >= min_bp => {
+                    let (rhs, rest) = self.expr(rest, right)?;
+                    lhs = (self.f)(Operation::Infix(lhs, op, rhs));
+                    input = rest;
+                }
+                _ => return Ok((lhs, input)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc<'a>() -> BoxedParser<'a, i64> {
+        Operators::new()
+            .infix("-", 1, Assoc::Left)
+            .infix("^", 2, Assoc::Right)
+            .postfix("!", 3)
+            .fold(integer::<i64>(), |op| match op {
+                Operation::Infix(l, "-", r) => l - r,
+                Operation::Infix(l, "^", r) => l.pow(r as u32),
+                Operation::Postfix(v, "!") => (1..=v).product(),
+                _ => unreachable!(),
+            })
+    }
+
+    #[test]
+    fn test_pratt_associativity() {
+        assert_eq!(calc().parse("10-3-2"), Ok((5, "")));
+        assert_eq!(calc().parse("2^3^2"), Ok((512, "")));
+        assert_eq!(calc().parse("3!^2-1 rest"), Ok((35, " rest")));
+    }
+
+    #[test]
+    fn test_pratt_longest_operator_wins() {
+        let compare = Operators::new()
+            .infix("<", 1, Assoc::Left)
+            .infix("<=", 1, Assoc::Left)
+            .infix("<<", 2, Assoc::Left)
+            .prefix("-", 3)
+            .prefix("--", 3)
+            .fold(integer::<i64>(), |op| match op {
+                Operation::Infix(l, "<", r) => (l < r) as i64,
+                Operation::Infix(l, "<=", r) => (l <= r) as i64,
+                Operation::Infix(l, "<<", r) => l << r,
+                Operation::Prefix("-", v) => -v,
+                Operation::Prefix("--", v) => v - 1,
+                _ => unreachable!(),
+            });
+        assert_eq!(compare.parse_result("2<=2"), Ok(1));
+        assert_eq!(compare.parse_result("2<2"), Ok(0));
+        assert_eq!(compare.parse_result("1<<3<=8"), Ok(1));
+        assert_eq!(compare.parse_result("--3<=2"), Ok(1));
+    }
+
+    #[test]
+    fn test_pratt_missing_operand() {
+        let err = calc().parse_result("1-2-").unwrap_err();
+        assert_eq!(err.offset, 4);
+        assert_eq!(err.expected, vec![Expected::named("integer")]);
+    }
+}
@@ -0,0 +1,189 @@
+/*
+    self-referential grammars. the problem is that combinators own their parsers, so a parser can't contain itself,
+    and a parser built by a function that calls itself would have an infinitely large type.
+
+    `recursive` solves the first one by building the parser inside an Rc, and handing the definition a weak reference
+    to it. the parser owns the only strong reference, so there's no cycle, and dropping the parser frees everything.
+
+    `lazy` solves the second one by boxing up the function that builds the parser, and not calling it until the
+    first time the parser is actually used. so its type doesn't depend on what it builds.
+*/
+use std::cell::{Cell, OnceCell};
+use std::rc::{Rc, Weak};
+
+use super::*;
+
+type Slot<'a, T, I> = OnceCell<BoxedParser<'a, T, I>>;
+type Make<'a, T, I> = Box<dyn FnOnce() -> BoxedParser<'a, T, I> + 'a>;
+
+/// A Parser that can refer to itself, made by `recursive`
+pub struct Recursive<'a, T, I = &'a str> {
+    parser: Rc<Slot<'a, T, I>>,
+}
+
+/// A reference to a `Recursive` parser from inside its own definition. Clone it as many times as the grammar needs
+pub struct RecursiveRef<'a, T, I = &'a str> {
+    parser: Weak<Slot<'a, T, I>>,
+}
+
+impl<'a, T, I> Clone for RecursiveRef<'a, T, I> {
+    fn clone(&self) -> Self {
+        Self { parser: self.parser.clone() }
+    }
+}
+
+/// Creates a Parser that can refer to itself. `define` gets a reference to the parser being defined, and
+/// returns its definition, which can use that reference anywhere it could use any other parser.
+///
+/// Each level of nesting in the input is another trip down the call stack, so input nested hundreds deep is
+/// fine, but something nested hundreds of thousands deep will overflow it
+/// ```
+/// use lib::parse::*;
+///
+/// // how deeply nested a run of balanced parentheses gets, like "(()(()))"
+/// let depth = recursive(|parens| {
+///     "(".but_really(parens.clone()).skip(")")
+///         .map(|inner| inner + 1)
+///         .repeat(Any)
+///         .map(|depths| depths.into_iter().max().unwrap_or(0))
+/// });
+/// assert_eq!(depth.parse("(()(()))"), Ok((3, "")));
+/// assert_eq!(depth.parse("()()x"), Ok((1, "x")));
+/// // an unclosed "(" isn't part of the run at all
+/// assert_eq!(depth.parse("(()"), Ok((0, "(()")));
+/// ```
+pub fn recursive<'a, T: 'a, I: Input<'a> + 'a, P: Parser<'a, T, I> + 'a>(
+    define: impl FnOnce(RecursiveRef<'a, T, I>) -> P,
+) -> Recursive<'a, T, I> {
+    let parser = Rc::new(OnceCell::new());
+    let definition = define(RecursiveRef { parser: Rc::downgrade(&parser) });
+    if parser.set(definition.boxed()).is_err() {
+        unreachable!("nothing else can fill in a recursive parser")
+    }
+    Recursive { parser }
+}
+
+impl<'a, T, I: Input<'a>> Parser<'a, T, I> for Recursive<'a, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.parser.get().expect("a recursive parser is always defined by the time anyone has it").parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.parser.get().expect("a recursive parser is always defined by the time anyone has it").parse_furthest(input, passed)
+    }
+
+    fn grammar(&self) -> Grammar {
+        let parser = self.parser.get().expect("a recursive parser is always defined by the time anyone has it");
+        Grammar::Recursive(Rc::as_ptr(&self.parser) as usize, Box::new(parser.grammar()))
+    }
+}
+
+impl<'a, T, I: Input<'a>> Parser<'a, T, I> for RecursiveRef<'a, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        let parser = self.parser.upgrade()
+            .expect("a recursive parser can only be used through its own definition while it's alive");
+        let parser = parser.get()
+            .expect("a recursive parser can't be used while it's still being defined");
+        parser.parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        let parser = self.parser.upgrade()
+            .expect("a recursive parser can only be used through its own definition while it's alive");
+        let parser = parser.get()
+            .expect("a recursive parser can't be used while it's still being defined");
+        parser.parse_furthest(input, passed)
+    }
+
+    // the definition is what's asking, so it already knows what we are
+    fn grammar(&self) -> Grammar {
+        Grammar::Ref(self.parser.as_ptr() as usize)
+    }
+}
+
+/// A Parser that isn't built until it's first used, made by `lazy`
+pub struct Lazy<'a, T, I = &'a str> {
+    make: Cell<Option<Make<'a, T, I>>>,
+    parser: OnceCell<BoxedParser<'a, T, I>>,
+}
+
+/// Creates a Parser that calls `make` to build the real parser the first time it's needed. This is how a
+/// grammar written as functions can refer to rules that call back into themselves, or that are defined later,
+/// without their types becoming infinitely large
+/// ```
+/// use lib::parse::*;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Bag { Empty, Holding(Vec<Bag>) }
+///
+/// fn bag<'a>() -> impl Parser<'a, Bag> {
+///     "[]".map(|_| Bag::Empty).or(contents().map(Bag::Holding))
+/// }
+/// fn contents<'a>() -> impl Parser<'a, Vec<Bag>> {
+///     "[".but_really(lazy(bag).repeat_delimited(Many, ",")).skip("]")
+/// }
+///
+/// use Bag::*;
+/// assert_eq!(bag().parse("[[],[[]]]"), Ok((Holding(vec![Empty, Holding(vec![Empty])]), "")));
+/// ```
+pub fn lazy<'a, T, I: Input<'a>, P: Parser<'a, T, I> + 'a>(make: impl FnOnce() -> P + 'a) -> Lazy<'a, T, I> {
+    Lazy {
+        make: Cell::new(Some(Box::new(move || make().boxed()))),
+        parser: OnceCell::new(),
+    }
+}
+
+impl<'a, T, I: Input<'a>> Lazy<'a, T, I> {
+    fn built(&self) -> &BoxedParser<'a, T, I> {
+        self.parser.get_or_init(|| {
+            let make = self.make.take().expect("a lazy parser can't be used while it's still being built");
+            make()
+        })
+    }
+}
+
+impl<'a, T, I: Input<'a>> Parser<'a, T, I> for Lazy<'a, T, I> {
+    fn parse(&self, input: I) -> Result<'a, T, I> {
+        self.built().parse(input)
+    }
+
+    fn parse_furthest(&self, input: I, passed: &mut Option<Error>) -> Result<'a, T, I> {
+        self.built().parse_furthest(input, passed)
+    }
+
+    // building the parser just to describe it could go on forever, since that's why it's lazy in the first place
+    fn grammar(&self) -> Grammar {
+        self.parser.get().map_or(Grammar::Opaque, |parser| parser.grammar())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested<'a>() -> Recursive<'a, usize> {
+        recursive(|inner| "(".but_really(inner.clone()).skip(")").map(|n| n + 1).or("x".map(|_| 0)))
+    }
+
+    // every level of nesting is a handful of stack frames, so this is about as deep as a debug build
+    // gets on the 2MB stack test threads have. release builds manage several thousand
+    #[test]
+    fn test_recursive_deep_nesting() {
+        let depth = 500;
+        let input = "(".repeat(depth) + "x" + &")".repeat(depth);
+        assert_eq!(nested().parse_result(&input), Ok(depth));
+    }
+
+    #[test]
+    fn test_recursive_does_not_leak() {
+        let marker = Rc::new(());
+        let parser = {
+            let marker = marker.clone();
+            recursive(move |inner| "(".but_really(inner.clone()).skip(")").or("x").map(move |s| { let _ = &marker; s }))
+        };
+        assert_eq!(parser.parse("((x))"), Ok(("x", "")));
+        assert_eq!(Rc::strong_count(&marker), 2);
+        drop(parser);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+}
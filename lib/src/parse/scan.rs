@@ -0,0 +1,199 @@
+/*
+    scanf for parsers. `scan!("{}-{} {}: {}", u32, u32, char, String)` is the same parser as
+    uint32().skip("-").then(uint32()).skip(" ").then(character())... except it comes out as a flat (u32, u32, char, String)
+    instead of (((u32, u32), char), String), which is the whole point.
+
+    the pattern gets split into the literal text between the {}s once, when the parser is made, and each {} is
+    parsed with that type's Parse impl. the number of {}s is checked against the number of types at compile time.
+*/
+use std::borrow::Cow;
+
+use super::*;
+
+/// Creates a Parser from a pattern, where each `{}` is a value of the next type in the list, parsed with that
+/// type's `Parse` impl, and everything else has to match exactly (`{{` and `}}` are literal braces). It produces
+/// a flat tuple of all the values, in order
+/// ```
+/// use lib::parse::*;
+///
+/// let policy = scan!("{}-{} {}: {}", u32, u32, char, String);
+/// assert_eq!(policy.parse_result("1-3 a: abcde"), Ok((1, 3, 'a', "abcde".to_string())));
+///
+/// let err = policy.parse_result("1-3 a - abcde").unwrap_err();
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected \": \", found ' '"));
+/// ```
+#[macro_export]
+macro_rules! scan {
+    ($pattern:literal $(, $t:ty)* $(,)?) => {{
+        const _: () = assert!(
+            $crate::parse::count_placeholders($pattern) == 0 $(+ { stringify!($t); 1 })*,
+            "scan! needs exactly one type for each placeholder in its pattern"
+        );
+        $crate::parse::Scan::new($pattern, ($(<$t as $crate::parse::Parse>::parser(),)*))
+    }};
+}
+
+/// The Parser made by `scan!`, which produces a tuple of whatever its `parsers` do
+#[derive(Clone)]
+pub struct Scan<P> {
+    /// the text before, between and after each placeholder, so there's always one more of these than parsers
+    text: Vec<Cow<'static, str>>,
+    parsers: P,
+}
+
+impl<P> Scan<P> {
+    #[doc(hidden)]
+    pub fn new(pattern: &'static str, parsers: P) -> Self {
+        let mut text = vec![Cow::Borrowed("")];
+        let mut start = 0;
+        let mut chars = pattern.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let current = text.last_mut().unwrap();
+            match (c, chars.peek().map(|(_, c)| c)) {
+                ('{', Some('}')) => {
+                    chars.next();
+                    text.push(Cow::Borrowed(""));
+                    start = i + 2;
+                    continue;
+                }
+                // an escaped brace means this bit of text can't just borrow from the pattern any more
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    current.to_mut().push(c);
+                    start = i + 2;
+                    continue;
+                }
+                _ => {}
+            }
+            match current {
+                Cow::Borrowed(_) => *current = Cow::Borrowed(&pattern[start..i + c.len_utf8()]),
+                Cow::Owned(s) => s.push(c),
+            }
+        }
+        Self { text, parsers }
+    }
+
+    fn literal<'a>(&self, index: usize, input: &'a str) -> std::result::Result<&'a str, Error> {
+        let text = &self.text[index];
+        input.strip_prefix(text.as_ref()).ok_or_else(|| Error::new(input, Expected::Literal(text.clone())))
+    }
+
+    fn literal_grammar(&self, index: usize) -> Grammar {
+        match &self.text[index] {
+            text if text.is_empty() => Grammar::empty(),
+            text => Grammar::Literal(text.clone()),
+        }
+    }
+}
+
+/// how many `{}`s are in a `scan!` pattern, not counting escaped braces
+#[doc(hidden)]
+pub const fn count_placeholders(pattern: &str) -> usize {
+    let bytes = pattern.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], if i + 1 < bytes.len() { bytes[i + 1] } else { 0 }) {
+            (b'{', b'}') => count += 1,
+            (b'{', b'{') | (b'}', b'}') => {}
+            (b'{', _) => panic!("scan! placeholders have to be empty, and a literal brace has to be doubled"),
+            (b'}', _) => panic!("unmatched closing brace in scan! pattern, a literal brace has to be doubled"),
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    count
+}
+
+impl<'a> Parser<'a, ()> for Scan<()> {
+    fn parse(&self, input: &'a str) -> Result<'a, ()> {
+        Ok(((), self.literal(0, input)?))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.literal_grammar(0)
+    }
+}
+
+// Parser for Scan<(P0, P1, ...)>, for every size of tuple up to 12. each step adds one more parser onto the
+// list it was given, so the tuples come out in order
+macro_rules! impl_scan {
+    ([$(($p:ident $v:ident $t:ident $i:tt))*]) => {};
+    ([$(($p:ident $v:ident $t:ident $i:tt))*] ($np:ident $nv:ident $nt:ident $ni:tt) $($rest:tt)*) => {
+        impl<'a, $($t, $p: Parser<'a, $t>,)* $nt, $np: Parser<'a, $nt>> Parser<'a, ($($t,)* $nt,)> for Scan<($($p,)* $np,)> {
+            fn parse(&self, input: &'a str) -> Result<'a, ($($t,)* $nt,)> {
+                let input = self.literal(0, input)?;
+                $(
+                    let ($v, input) = self.parsers.$i.parse(input)?;
+                    let input = self.literal($i + 1, input)?;
+                )*
+                let ($nv, input) = self.parsers.$ni.parse(input)?;
+                let input = self.literal($ni + 1, input)?;
+                Ok((($($v,)* $nv,), input))
+            }
+
+            fn grammar(&self) -> Grammar {
+                let grammar = self.literal_grammar(0);
+                $(
+                    let grammar = grammar.then(self.parsers.$i.grammar()).then(self.literal_grammar($i + 1));
+                )*
+                grammar.then(self.parsers.$ni.grammar()).then(self.literal_grammar($ni + 1))
+            }
+        }
+        impl_scan!([$(($p $v $t $i))* ($np $nv $nt $ni)] $($rest)*);
+    };
+}
+
+impl_scan!([]
+    (P0 v0 T0 0) (P1 v1 T1 1) (P2 v2 T2 2) (P3 v3 T3 3) (P4 v4 T4 4) (P5 v5 T5 5)
+    (P6 v6 T6 6) (P7 v7 T7 7) (P8 v8 T8 8) (P9 v9 T9 9) (P10 v10 T10 10) (P11 v11 T11 11)
+);
+
+/// Runs `parser` over every line of `input`, where it has to match the whole line. Errors point at the right
+/// line of the whole input, not just the line that failed
+/// ```
+/// use lib::parse::*;
+///
+/// let input = "1-3 a\n1-3 b\n2-9 c\n";
+/// let policies = scan_lines(input, scan!("{}-{} {}", usize, usize, char)).collect::<std::result::Result<Vec<_>, _>>();
+/// assert_eq!(policies, Ok(vec![(1, 3, 'a'), (1, 3, 'b'), (2, 9, 'c')]));
+///
+/// let err = scan_lines("1-3 a\n1-3 bb\n", scan!("{}-{} {}", usize, usize, char)).nth(1).unwrap().unwrap_err();
+/// assert_eq!((err.line, err.column), (2, 6));
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected end of line, found 'b'"));
+/// ```
+pub fn scan_lines<'a, T: 'a>(
+    input: &'a str,
+    parser: impl Parser<'a, T> + 'a,
+) -> impl Iterator<Item = std::result::Result<T, ParseError>> + 'a {
+    input.lines().map(move |line| {
+        // everything after this line, which we need to say where on this line an error is, in all of `input`
+        let after = input.len() - (line.as_ptr() as usize - input.as_ptr() as usize) - line.len();
+        let result = match parser.parse(line) {
+            Ok((val, "")) => Ok(val),
+            Ok((_, rest)) => Err(Error::new(rest, Expected::named("end of line"))),
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| {
+            let remaining = e.remaining() + after;
+            e.moved_to(remaining).locate(input)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_escaped_braces_and_unicode() {
+        let point = scan!("{{é{}, {}}}→", i32, i32);
+        assert_eq!(point.parse("{é-1, 2}→!"), Ok(((-1, 2), "!")));
+        assert_eq!(scan!("just text").parse("just text"), Ok(((), "")));
+        let err = point.parse_result("{é-1, 2]").unwrap_err();
+        assert_eq!((err.offset, err.expected), (8, vec![Expected::literal("}→")]));
+    }
+}
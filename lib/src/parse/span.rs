@@ -0,0 +1,98 @@
+use std::ops::Range;
+
+use super::Input;
+
+/// Where a parsed value came from in the original input.
+///
+/// Like `Error`, a span is recorded as how much input was left at either end of it, since that's
+/// all a parser can see. That stays the same no matter how deep in a chain of parsers the span
+/// was taken, so handing it the original input gets you back offsets, line numbers and the text itself.
+/// ```
+/// use lib::parse::*;
+/// let input = "byr:1937\nhgt:183\n";
+/// let field = identifier_str().skip(":").then(uint32()).spanned();
+/// let parser = field.repeat_delimited(Many, "\n");
+///
+/// let fields = parser.parse_result(input).unwrap();
+/// let (_, span) = &fields[1];
+/// assert_eq!(span.range(input), 9..16);
+/// assert_eq!(span.slice(input), "hgt:183");
+/// assert_eq!(span.start_line_col(input), LineCol { line: 2, column: 1 });
+/// assert_eq!(span.end_line_col(input), LineCol { line: 2, column: 8 });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    remaining_at_start: usize,
+    remaining_at_end: usize,
+}
+
+impl Span {
+    /// the span covering whatever was consumed going from `input` to `rest`
+    pub fn between<'a, I: Input<'a>>(input: I, rest: I) -> Self {
+        Self { remaining_at_start: input.input_len(), remaining_at_end: rest.input_len() }
+    }
+
+    /// length of the span, in bytes for text, or items for slices
+    pub fn len(&self) -> usize {
+        self.remaining_at_start - self.remaining_at_end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the smallest span covering both this and `other`
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            remaining_at_start: self.remaining_at_start.max(other.remaining_at_start),
+            remaining_at_end: self.remaining_at_end.min(other.remaining_at_end),
+        }
+    }
+
+    /// offset of the start of the span in `source`
+    pub fn start<'a>(&self, source: impl Input<'a>) -> usize {
+        source.input_len() - self.remaining_at_start
+    }
+
+    /// offset just past the end of the span in `source`
+    pub fn end<'a>(&self, source: impl Input<'a>) -> usize {
+        source.input_len() - self.remaining_at_end
+    }
+
+    pub fn range<'a>(&self, source: impl Input<'a>) -> Range<usize> {
+        self.start(source)..self.end(source)
+    }
+
+    /// the part of `source` this span covers
+    pub fn slice<'a, I: Input<'a>>(&self, source: I) -> I {
+        let (_, from_start) = source.split_at(self.start(source));
+        from_start.split_at(self.len()).0
+    }
+
+    pub fn start_line_col(&self, source: &str) -> LineCol {
+        LineCol::at(source, self.start(source))
+    }
+
+    pub fn end_line_col(&self, source: &str) -> LineCol {
+        LineCol::at(source, self.end(source))
+    }
+}
+
+/// A position in some source text, both 1-based, with the column counted in chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineCol {
+    /// works out the line and column of byte `offset` in `source`
+    pub fn at(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
@@ -0,0 +1,282 @@
+/*
+    parsing from a BufRead a line (or a record) at a time, instead of needing the whole input in one string.
+
+    the catch is that everything parsed out of a line has to outlive the line, since the buffer gets reused
+    for the next one. so instead of a parser, these take a function that parses one line, which has to work
+    for a line of any lifetime and can't hand back anything borrowed from it. that's usually just a closure
+    that builds the parser and runs it, like `|line| uint32().parse(line)`, written right in the call, since
+    rust only works out that a closure takes any lifetime when it can see what it's being passed to.
+
+    errors are located within the line or record they came from, and then moved to where that was in the
+    whole stream, so line numbers in error messages are still right.
+
+    "\r\n" line endings come through as "\n", inside records as well as at the end of them, so a parser that
+    splits a record on "\n" works the same on input from windows. offsets still count the "\r"s that were taken out.
+*/
+use std::fmt;
+use std::io::{self, BufRead};
+
+use super::*;
+
+/// Something that went wrong partway through a stream: either reading it, or parsing what was read
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "error: {}", e),
+            StreamError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+impl From<io::Error> for StreamError {
+    fn from(e: io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+/// Lazily parses every line of `reader` with `parse`, which has to match the whole line. Each item comes with
+/// its 1-based line number, and only one line is ever held in memory at a time
+/// ```
+/// use lib::parse::*;
+///
+/// let reader = "1-3 a\n1-3 b\r\n2-9 c\n".as_bytes();
+/// let mut policies = parse_lines(reader, |line| scan!("{}-{} {}", u32, u32, char).parse(line));
+/// assert_eq!(policies.next().unwrap().unwrap(), (1, (1, 3, 'a')));
+/// assert_eq!(policies.next().unwrap().unwrap(), (2, (1, 3, 'b')));
+/// assert_eq!(policies.next().unwrap().unwrap(), (3, (2, 9, 'c')));
+/// assert!(policies.next().is_none());
+///
+/// let reader = "1\n2\nthree\n4".as_bytes();
+/// let errors = parse_lines(reader, |line| uint32().parse(line)).filter_map(|r| r.err()).collect::<Vec<_>>();
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].to_string().lines().nth(1), Some(" --> 3:1"));
+/// ```
+pub fn parse_lines<R: BufRead, T, F: for<'a> Fn(&'a str) -> Result<'a, T>>(reader: R, parse: F) -> ParsedLines<R, F> {
+    ParsedLines { chunks: Chunks::new(reader), parse }
+}
+
+/// Lazily parses every blank-line-separated record in `reader` with `parse`, which has to match the whole
+/// record. Each item comes with the 1-based line number the record starts on, and only one record is ever
+/// held in memory at a time
+/// ```
+/// use lib::parse::*;
+///
+/// let reader = "\n1 2\n3\n\n4\n\n\n5 6\n".as_bytes();
+/// let sums = parse_records(reader, |text| {
+///     uint32().repeat_delimited(Many, " ".or("\n")).map(|ns| ns.iter().sum::<u32>()).parse(text)
+/// });
+/// let sums = sums.collect::<std::result::Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(sums, vec![(2, 6), (5, 4), (8, 11)]);
+/// ```
+pub fn parse_records<R: BufRead, T, F: for<'a> Fn(&'a str) -> Result<'a, T>>(reader: R, parse: F) -> ParsedRecords<R, F> {
+    ParsedRecords { chunks: Chunks::new(reader), parse }
+}
+
+/// The iterator made by `parse_lines`
+pub struct ParsedLines<R, F> {
+    chunks: Chunks<R>,
+    parse: F,
+}
+
+impl<R: BufRead, T, F: for<'a> Fn(&'a str) -> Result<'a, T>> Iterator for ParsedLines<R, F> {
+    type Item = std::result::Result<(usize, T), StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.chunks.next_line() {
+            Ok(true) => Some(self.chunks.parse(&self.parse, "end of line")),
+            Ok(false) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+/// The iterator made by `parse_records`
+pub struct ParsedRecords<R, F> {
+    chunks: Chunks<R>,
+    parse: F,
+}
+
+impl<R: BufRead, T, F: for<'a> Fn(&'a str) -> Result<'a, T>> Iterator for ParsedRecords<R, F> {
+    type Item = std::result::Result<(usize, T), StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.chunks.next_record() {
+            Ok(true) => Some(self.chunks.parse(&self.parse, "end of record")),
+            Ok(false) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+// reads the stream a piece at a time, keeping track of where the current piece started
+struct Chunks<R> {
+    reader: R,
+    buffer: String,
+    /// the line the current chunk starts on, and how many lines and bytes come before the next one
+    start_line: usize,
+    start_offset: usize,
+    lines_read: usize,
+    bytes_read: usize,
+    /// where in the buffer a "\r" got taken out of a "\r\n", so offsets can put it back
+    removed: Vec<usize>,
+}
+
+impl<R: BufRead> Chunks<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, buffer: String::new(), start_line: 0, start_offset: 0, lines_read: 0, bytes_read: 0, removed: Vec::new() }
+    }
+
+    // reads the next line onto the end of the buffer, with a "\r\n" turned into "\n", or says there aren't any more
+    fn read_line(&mut self) -> io::Result<bool> {
+        let n = self.reader.read_line(&mut self.buffer)?;
+        self.bytes_read += n;
+        if n > 0 {
+            self.lines_read += 1;
+        }
+        if self.buffer.ends_with("\r\n") {
+            let cr = self.buffer.len() - 2;
+            self.buffer.remove(cr);
+            self.removed.push(cr);
+        }
+        Ok(n > 0)
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.removed.clear();
+    }
+
+    fn next_line(&mut self) -> io::Result<bool> {
+        self.clear();
+        self.start_line = self.lines_read + 1;
+        self.start_offset = self.bytes_read;
+        let more = self.read_line()?;
+        trim_newline(&mut self.buffer);
+        Ok(more)
+    }
+
+    // a record is every line up to the next blank one, not counting any blank lines before it
+    fn next_record(&mut self) -> io::Result<bool> {
+        loop {
+            self.clear();
+            self.start_line = self.lines_read + 1;
+            self.start_offset = self.bytes_read;
+            if !self.read_line()? {
+                return Ok(false);
+            }
+            if !self.buffer.trim().is_empty() {
+                break;
+            }
+        }
+        loop {
+            let line_start = self.buffer.len();
+            if !self.read_line()? {
+                break;
+            }
+            if self.buffer[line_start..].trim().is_empty() {
+                self.buffer.truncate(line_start);
+                break;
+            }
+        }
+        trim_newline(&mut self.buffer);
+        Ok(true)
+    }
+
+    fn parse<T>(&self, parse: impl for<'a> Fn(&'a str) -> Result<'a, T>, end: &'static str) -> std::result::Result<(usize, T), StreamError> {
+        let text = self.buffer.as_str();
+        let result = match parse(text) {
+            Ok((val, "")) => Ok(val),
+            Ok((_, rest)) => Err(Error::new(rest, Expected::named(end))),
+            Err(e) => Err(e),
+        };
+        result
+            .map(|val| (self.start_line, val))
+            .map_err(|e| {
+                let mut e = e.locate(text);
+                e.line += self.start_line - 1;
+                e.offset += self.start_offset + self.removed.iter().filter(|&&cr| cr < e.offset).count();
+                StreamError::Parse(e)
+            })
+    }
+}
+
+// any "\r" before it has already gone, in read_line
+fn trim_newline(s: &mut String) {
+    if s.ends_with('\n') {
+        s.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a reader that makes up its input as it goes, so there's never a copy of the whole thing anywhere
+    struct Generated {
+        lines_left: usize,
+        pending: Vec<u8>,
+    }
+
+    impl io::Read for Generated {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pending.is_empty() && self.lines_left > 0 {
+                self.pending = format!("{}\n", self.lines_left % 1000).into_bytes();
+                self.lines_left -= 1;
+            }
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_parse_lines_streams() {
+        let reader = io::BufReader::new(Generated { lines_left: 200_000, pending: Vec::new() });
+        let mut sum = 0u64;
+        let mut last_line = 0;
+        for item in parse_lines(reader, |line| integer::<u64>().parse(line)) {
+            let (line, n) = item.unwrap();
+            sum += n;
+            last_line = line;
+        }
+        assert_eq!(last_line, 200_000);
+        assert_eq!(sum, (0..200_000u64).map(|n| (n + 1) % 1000).sum());
+    }
+
+    #[test]
+    fn test_parse_records_crlf() {
+        let sums = |input: &str| parse_records(input.as_bytes(), |text| {
+            uint32().repeat_delimited(Many, " ".or("\n")).map(|ns| ns.iter().sum::<u32>()).parse(text)
+        }).map(|r| r.unwrap()).collect::<Vec<_>>();
+        assert_eq!(sums("1 2\r\n3\r\n\r\n4\r\n"), vec![(1, 6), (4, 4)]);
+        assert_eq!(sums("1 2\r\n3\r\n\r\n4\r\n"), sums("1 2\n3\n\n4\n"));
+
+        // the error is on the "x", counting every "\r" before it
+        let err = parse_records("1\r\n2\r\n\r\n3\r\nx\r\n".as_bytes(), |text| uint32().skip("\n").then(uint32()).parse(text))
+            .nth(1).unwrap().unwrap_err();
+        match err {
+            StreamError::Parse(e) => assert_eq!((e.offset, e.line, e.column), (11, 5, 1)),
+            StreamError::Io(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_parse_records_error_offsets() {
+        let input = "1\n2\n\n3\nx\n";
+        let err = parse_records(input.as_bytes(), |text| uint32().skip("\n").then(uint32()).parse(text))
+            .nth(1).unwrap().unwrap_err();
+        match err {
+            StreamError::Parse(e) => assert_eq!((e.offset, e.line, e.column), (7, 5, 1)),
+            StreamError::Io(e) => panic!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+/*
+    tracing, for when a parser fails and the error alone doesn't say why. every `.named()` or `.trace()`
+    parser reports when it starts and what happened, to a collector for the current thread, which builds
+    those up into a tree of everything that was tried.
+
+    nothing gets recorded unless something is collecting, and the first thing a traced parser does is check
+    a global count of collectors, so all of this costs next to nothing the rest of the time.
+*/
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::*;
+
+/// One named parser's attempt at the input, and every named parser it ran along the way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+    pub name: &'static str,
+    /// what it matched, or for a failure, from where it started up to where it failed
+    pub span: Span,
+    /// None if it matched, otherwise what it expected at the end of `span`
+    pub failure: Option<Vec<Expected>>,
+    pub children: Vec<TraceNode>,
+}
+
+/// Everything traced while `collect_trace` was running
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Trace {
+    pub roots: Vec<TraceNode>,
+}
+
+/// Runs `f`, recording every `.named()` and `.trace()` parser that runs on this thread in the meantime
+/// ```
+/// use lib::parse::*;
+///
+/// let range = uint32().trace("low").skip("-").then(uint32().trace("high")).trace("range");
+/// let input = "1-x";
+/// let (result, trace) = collect_trace(|| range.parse(input));
+/// assert!(result.is_err());
+/// assert_eq!(trace.render(input), "\
+/// range 1:1 failed at 1:3, expected unsigned integer
+///   low 1:1 matched \"1\"
+///     unsigned integer 1:1 matched \"1\"
+///   high 1:3 failed at 1:3, expected unsigned integer
+///     unsigned integer 1:3 failed at 1:3, expected unsigned integer
+/// ");
+/// ```
+pub fn collect_trace<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    // put back whatever was collecting before, even if `f` panics
+    struct Restore(Option<Collector>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            COLLECTOR.with(|c| *c.borrow_mut() = self.0.take());
+            COLLECTING.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    COLLECTING.fetch_add(1, Ordering::Relaxed);
+    let previous = COLLECTOR.with(|c| c.borrow_mut().replace(Collector::default()));
+    let restore = Restore(previous);
+    let result = f();
+    let collector = COLLECTOR.with(|c| c.borrow_mut().take()).unwrap_or_default();
+    drop(restore);
+    (result, Trace { roots: collector.finished })
+}
+
+/// how many threads are collecting right now, so everyone else can skip the thread local entirely
+static COLLECTING: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct Collector {
+    /// parsers that have started, but not finished, innermost last
+    running: Vec<TraceNode>,
+    finished: Vec<TraceNode>,
+}
+
+/// runs `parse`, recording it as `name` if anything is collecting
+pub(super) fn traced<'a, T, I: Input<'a>>(name: &'static str, input: I, parse: impl FnOnce() -> Result<'a, T, I>) -> Result<'a, T, I> {
+    if COLLECTING.load(Ordering::Relaxed) == 0 {
+        return parse();
+    }
+
+    let started = COLLECTOR.with(|c| match c.borrow_mut().as_mut() {
+        Some(collector) => {
+            collector.running.push(TraceNode { name, span: Span::between(input, input), failure: None, children: Vec::new() });
+            true
+        }
+        None => false,
+    });
+    let result = parse();
+    if started {
+        COLLECTOR.with(|c| {
+            if let Some(collector) = c.borrow_mut().as_mut() {
+                let mut node = collector.running.pop().expect("every traced parser that starts also finishes");
+                node.span = match &result {
+                    Ok((_, rest)) => Span::between(input, *rest),
+                    Err(e) => node.span.merge(Span::between(input, input.split_at(input.input_len() - e.remaining()).1)),
+                };
+                node.failure = result.as_ref().err().map(|e| e.expected().cloned().collect());
+                match collector.running.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => collector.finished.push(node),
+                }
+            }
+        });
+    }
+    result
+}
+
+impl Trace {
+    /// the trace as an indented tree, with one line per parser, saying where it started, and what it matched
+    /// or where it failed. `source` has to be the input that was being parsed
+    pub fn render(&self, source: &str) -> String {
+        let mut out = String::new();
+        for node in &self.roots {
+            node.render(source, 0, &mut out);
+        }
+        out
+    }
+
+    /// the trace as JSON: a list of nodes, each with its name, start and end offsets into `source`, whether it
+    /// matched, what it matched, what it expected if it failed, and its children
+    /// ```
+    /// use lib::parse::*;
+    ///
+    /// let (_, trace) = collect_trace(|| "a".trace("letter").parse("a\"b"));
+    /// assert_eq!(
+    ///     trace.to_json("a\"b"),
+    ///     r#"[{"name":"letter","start":0,"end":1,"matched":true,"text":"a","expected":[],"children":[]}]"#,
+    /// );
+    /// ```
+    pub fn to_json(&self, source: &str) -> String {
+        let mut out = String::new();
+        json_list(&self.roots, source, &mut out);
+        out
+    }
+}
+
+impl TraceNode {
+    fn render(&self, source: &str, depth: usize, out: &mut String) {
+        let start = self.span.start_line_col(source);
+        let _ = write!(out, "{:indent$}{} {}:{}", "", self.name, start.line, start.column, indent = depth * 2);
+        let _ = match &self.failure {
+            None => writeln!(out, " matched {:?}", self.span.slice(source)),
+            Some(expected) => {
+                let end = self.span.end_line_col(source);
+                let expected = expected.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                writeln!(out, " failed at {}:{}, expected {}", end.line, end.column, expected.join(" or "))
+            }
+        };
+        for child in &self.children {
+            child.render(source, depth + 1, out);
+        }
+    }
+}
+
+fn json_list(nodes: &[TraceNode], source: &str, out: &mut String) {
+    out.push('[');
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let range = node.span.range(source);
+        let _ = write!(out, r#"{{"name":{},"start":{},"end":{},"matched":{},"text":{},"expected":["#,
+            json_string(node.name), range.start, range.end, node.failure.is_none(), json_string(node.span.slice(source)));
+        let expected = node.failure.iter().flatten().map(|e| json_string(&e.to_string())).collect::<Vec<_>>();
+        out.push_str(&expected.join(","));
+        out.push_str(r#"],"children":"#);
+        json_list(&node.children, source, out);
+        out.push('}');
+    }
+    out.push(']');
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_nothing_when_not_collecting() {
+        let parser = "a".trace("a");
+        assert_eq!(parser.parse("a"), Ok(("a", "")));
+        let (_, trace) = collect_trace(|| ());
+        assert!(trace.roots.is_empty());
+    }
+
+    #[test]
+    fn test_trace_nested_collectors() {
+        let (inner, outer) = collect_trace(|| {
+            "x".trace("outer").parse("x").unwrap();
+            collect_trace(|| "y".trace("inner").parse("y").unwrap()).1
+        });
+        assert_eq!(outer.roots.iter().map(|n| n.name).collect::<Vec<_>>(), vec!["outer"]);
+        assert_eq!(inner.roots.iter().map(|n| n.name).collect::<Vec<_>>(), vec!["inner"]);
+    }
+}
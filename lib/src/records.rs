@@ -0,0 +1,252 @@
+/*
+    blank-line-separated records of key:value pairs, like the passports in day4. the format is a bit too loose
+    to be nice to write as a grammar (a newline might separate two pairs, or end a record if the next line is
+    blank, or be part of a windows line ending, or just be the end of the file), so this walks the input line
+    by line instead. it's still a Parser though, so it reports problems the same way everything else does.
+*/
+use crate::parse::*;
+
+/// One key/value pair out of a `Record`, along with where each half came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub key_span: Span,
+    pub value_span: Span,
+}
+
+/// A record's fields, in the order they were written. Keys are unique within a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<'a> {
+    fields: Vec<Field<'a>>,
+    /// from the start of the first field to the end of the last
+    pub span: Span,
+}
+
+impl<'a> Record<'a> {
+    /// the value for `key`, if this record has one
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.field(key).map(|f| f.value)
+    }
+
+    /// the whole field for `key`, if this record has one
+    pub fn field(&self, key: &str) -> Option<&Field<'a>> {
+        // records are a handful of fields, so this beats hashing
+        self.fields.iter().find(|f| f.key == key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.field(key).is_some()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.fields.iter().map(|f| f.key)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Field<'a>> {
+        self.fields.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl<'a, 'r> IntoIterator for &'r Record<'a> {
+    type Item = &'r Field<'a>;
+    type IntoIter = std::slice::Iter<'r, Field<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fields.iter()
+    }
+}
+
+/// Reads records made of `key:value` pairs, separated by spaces or newlines, with a blank line between records.
+///
+/// Both separators can be changed. Windows line endings, blank lines at either end, and a missing newline at
+/// the end are all fine. A pair without a key/value separator, or a key that shows up twice in one record,
+/// is an error.
+/// ```
+/// use lib::records::*;
+///
+/// let input = "ecl:gry pid:860033327\r\nbyr:1937\r\n\r\nhcl:#cfa07d byr:1929\r\n";
+/// let records = Records::new().read(input).unwrap();
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(records[0].keys().collect::<Vec<_>>(), vec!["ecl", "pid", "byr"]);
+/// assert_eq!(records[1].get("hcl"), Some("#cfa07d"));
+///
+/// let field = records[0].field("byr").unwrap();
+/// assert_eq!(field.value_span.start_line_col(input).line, 2);
+///
+/// let err = Records::new().read("a:1 b:2\nc:3 a:4").unwrap_err();
+/// assert_eq!((err.line, err.column), (2, 5));
+/// assert_eq!(err.to_string().lines().next(), Some("error: expected a key other than \"a\", which this record already has, found 'a'"));
+///
+/// let other = Records::new().key_value_separator("=").pair_separator(", ").read("x=1, y=2\n\nx=3").unwrap();
+/// assert_eq!(other.iter().map(|r| r.get("x")).collect::<Vec<_>>(), vec![Some("1"), Some("3")]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Records {
+    key_value_separator: &'static str,
+    pair_separator: &'static str,
+}
+
+impl Records {
+    pub fn new() -> Self {
+        Self { key_value_separator: ":", pair_separator: " " }
+    }
+
+    /// what goes between a key and its value, `:` by default. keys end at the first one of these
+    pub fn key_value_separator(mut self, separator: &'static str) -> Self {
+        assert!(!separator.is_empty(), "records need a key/value separator");
+        self.key_value_separator = separator;
+        self
+    }
+
+    /// what goes between pairs on the same line, ` ` by default. the end of a line always separates pairs too
+    pub fn pair_separator(mut self, separator: &'static str) -> Self {
+        assert!(!separator.is_empty(), "records need a pair separator");
+        self.pair_separator = separator;
+        self
+    }
+
+    /// reads every record in `input`
+    pub fn read<'a>(&self, input: &'a str) -> std::result::Result<Vec<Record<'a>>, ParseError> {
+        self.parse_result(input)
+    }
+
+    /// a Parser for exactly one record, for when something else has already split them up, like `parse_records`.
+    /// a blank line followed by more fields is an error
+    /// ```
+    /// use lib::parse::*;
+    /// use lib::records::*;
+    ///
+    /// let record = Records::new().record();
+    /// assert_eq!(record.parse_result("a:1\nb:2\n").map(|r| r.len()), Ok(2));
+    ///
+    /// let err = record.parse_result("a:1\n\nb:2").unwrap_err();
+    /// assert_eq!((err.line, err.expected), (3, vec![Expected::named("end of record")]));
+    /// assert!(record.parse_result("\n").is_err());
+    /// ```
+    pub fn record<'a>(&self) -> impl Parser<'a, Record<'a>> + Clone {
+        let records = self.clone();
+        move |input: &'a str| {
+            let (mut read, rest) = records.parse(input)?;
+            match read.len() {
+                0 => Err(Error::new(input, Expected::named("record"))),
+                1 => Ok((read.remove(0), rest)),
+                _ => Err(Error::new(&input[read[1].span.start(input)..], Expected::named("end of record"))),
+            }
+        }
+    }
+
+    // `rest` is the whole rest of the input from the start of `pair`, for errors and spans
+    fn read_pair<'a>(&self, record: Option<&Record<'a>>, pair: &'a str, rest: &'a str) -> std::result::Result<Field<'a>, Error> {
+        let (key, value) = pair.split_once(self.key_value_separator)
+            .ok_or_else(|| Error::new(&rest[pair.len()..], Expected::static_literal(self.key_value_separator)))?;
+        if key.is_empty() {
+            return Err(Error::new(rest, Expected::named("key")));
+        }
+        if record.is_some_and(|r| r.contains_key(key)) {
+            return Err(Error::new(rest, Expected::named(format!("a key other than {:?}, which this record already has", key))));
+        }
+
+        let value_start = key.len() + self.key_value_separator.len();
+        let key_span = Span::between(rest, &rest[key.len()..]);
+        let value_span = Span::between(&rest[value_start..], &rest[pair.len()..]);
+        Ok(Field { key, value, key_span, value_span })
+    }
+}
+
+impl Default for Records {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// reading records is a parser that always reads the whole input
+impl<'a> Parser<'a, Vec<Record<'a>>> for Records {
+    fn parse(&self, input: &'a str) -> Result<'a, Vec<Record<'a>>> {
+        let mut records = Vec::new();
+        let mut current: Option<Record<'a>> = None;
+
+        let mut rest = input;
+        while !rest.is_empty() {
+            let (line, next) = match rest.find('\n') {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, &rest[rest.len()..]),
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+
+            if line.trim().is_empty() {
+                records.extend(current.take());
+            } else {
+                for pair in line.split(self.pair_separator).filter(|p| !p.is_empty()) {
+                    let pair_rest = &input[pair.as_ptr() as usize - input.as_ptr() as usize..];
+                    let field = self.read_pair(current.as_ref(), pair, pair_rest)?;
+                    match &mut current {
+                        Some(record) => {
+                            record.span = record.span.merge(field.value_span);
+                            record.fields.push(field);
+                        }
+                        None => current = Some(Record { span: field.key_span.merge(field.value_span), fields: vec![field] }),
+                    }
+                }
+            }
+            rest = next;
+        }
+        records.extend(current);
+        Ok((records, rest))
+    }
+
+    // keys and values are described as short runs of letters and digits, which is far narrower than what gets
+    // accepted, but it's what passports look like. the grammar can't say that keys are unique, so a sample
+    // can now and then repeat a key, which reading would reject
+    fn grammar(&self) -> Grammar {
+        let field = Grammar::pattern("[a-z]{3}")
+            .then(Grammar::literal(self.key_value_separator))
+            .then(Grammar::pattern("[0-9a-z#]{1,9}"))
+            .repeat(Between(1, 8), Grammar::literal(self.pair_separator).or(Grammar::literal("\n")));
+        Grammar::rule("records", Grammar::rule("record", field).repeat(Many, Grammar::literal("\n\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_value_at_end_of_input_and_spans() {
+        let input = "\n\nbyr:1937 hgt:183cm\n\n\n\neyr:2020";
+        let records = Records::new().read(input).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].span.slice(input), "byr:1937 hgt:183cm");
+        assert_eq!(records[1].get("eyr"), Some("2020"));
+        assert_eq!(records[1].field("eyr").unwrap().key_span.range(input), 24..27);
+    }
+
+    #[test]
+    fn test_records_missing_separator() {
+        let err = Records::new().read("byr:1937 hgt\n").unwrap_err();
+        assert_eq!((err.offset, err.expected), (12, vec![Expected::literal(":")]));
+    }
+
+    #[test]
+    fn test_records_grammar_samples_read_back() {
+        let records = Records::new();
+        assert_eq!(records.grammar().to_ebnf(), "\
+records ::= record (\"\\n\\n\" record)*
+record ::= /[a-z]{3}/ \":\" /[0-9a-z#]{1,9}/ ((\" \" | \"\\n\") /[a-z]{3}/ \":\" /[0-9a-z#]{1,9}/){0,7}
+");
+        let mut rng = crate::rng::Rng::new(4);
+        for _ in 0..20 {
+            let text = records.grammar().sample(&mut rng).unwrap();
+            let read = records.read(&text).unwrap();
+            assert_eq!(read.len(), text.split("\n\n").count());
+        }
+    }
+}
@@ -0,0 +1,91 @@
+/*
+    a small seeded random number generator, for anything that wants made-up input, like sampling a grammar.
+    there are no dependencies in this workspace, so no `rand`, but splitmix64 is a handful of lines and
+    more than random enough for test data. the same seed always gives the same numbers, on every platform,
+    which is the point: a seed in a failure message is enough to get the exact same input back.
+*/
+
+/// A seeded pseudo-random number generator (splitmix64). Not for anything that needs to be unpredictable
+/// ```
+/// use lib::rng::Rng;
+///
+/// let roll = |seed| {
+///     let mut rng = Rng::new(seed);
+///     (0..5).map(|_| rng.between(1, 6)).collect::<Vec<_>>()
+/// };
+/// assert!(roll(42).iter().all(|n| (1..=6).contains(n)));
+/// assert_eq!(roll(42), roll(42));
+/// assert_ne!(roll(42), roll(43));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// a number from 0 up to, but not including, `n`, with every one equally likely
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "there's nothing below 0 to pick");
+        // throw away the top sliver of numbers that don't divide evenly, so the low ones aren't favored
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// a number from `lo` to `hi`, inclusive
+    pub fn between(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo <= hi, "can't pick between {} and {}", lo, hi);
+        match (hi - lo).checked_add(1) {
+            Some(n) => lo + self.below(n),
+            None => self.next_u64(),
+        }
+    }
+
+    /// true, `p` of the time
+    pub fn chance(&mut self, p: f64) -> bool {
+        // the top 53 bits, as a fraction, which is every bit an f64 can hold
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// one of `items`, or None if there aren't any
+    pub fn choose<'t, T>(&mut self, items: &'t [T]) -> Option<&'t T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.below(items.len() as u64) as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_spread_out() {
+        let mut rng = Rng::new(7);
+        let mut counts = [0; 10];
+        for _ in 0..10_000 {
+            counts[rng.below(10) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&n| (900..1100).contains(&n)), "{:?}", counts);
+        assert_eq!(rng.between(5, 5), 5);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+    }
+}
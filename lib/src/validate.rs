@@ -0,0 +1,293 @@
+/*
+    rules about what the values in a record are allowed to be, like day4's passports, where a birth year has to
+    be from 1920 to 2002 and a height is either 150-193cm or 59-76in.
+
+    a Schema is a list of fields, each with a Rule, and checking some fields against it gets you a Report
+    saying how every field did, and why any that failed did, rather than a single yes or no. that makes it a lot
+    easier to work out which rule is wrong when the answer comes out off by one.
+*/
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::parse::*;
+use crate::records::Record;
+
+/// What a field's value has to look like
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// any value at all, for fields that only have to be there
+    Anything,
+    /// a whole number in the range, written as just digits, without a sign or leading zeros, and followed by the
+    /// unit (which can be empty)
+    Range(RangeInclusive<u64>, &'static str),
+    /// exactly one of these
+    OneOf(Vec<&'static str>),
+    /// the whole value matches this pattern
+    Matches(Pattern),
+    /// at least one of these rules
+    Either(Vec<Rule>),
+}
+
+impl Rule {
+    /// a whole number in `range`
+    pub fn range(range: RangeInclusive<u64>) -> Self {
+        Rule::Range(range, "")
+    }
+
+    /// a whole number in `range`, immediately followed by `unit`, like "183cm"
+    pub fn measure(range: RangeInclusive<u64>, unit: &'static str) -> Self {
+        Rule::Range(range, unit)
+    }
+
+    pub fn one_of(values: &[&'static str]) -> Self {
+        Rule::OneOf(values.to_vec())
+    }
+
+    /// the whole value matches `source`, which is a pattern as in `lib::parse::pattern`
+    pub fn matches(source: &str) -> Self {
+        Rule::Matches(pattern(source))
+    }
+
+    /// either this rule or `other`
+    pub fn or(self, other: Rule) -> Self {
+        match self {
+            Rule::Either(mut rules) => {
+                rules.push(other);
+                Rule::Either(rules)
+            }
+            rule => Rule::Either(vec![rule, other]),
+        }
+    }
+
+    /// Ok if `value` follows this rule, otherwise why it doesn't
+    pub fn check(&self, value: &str) -> std::result::Result<(), String> {
+        match self {
+            Rule::Anything => Ok(()),
+            Rule::Range(range, unit) => {
+                let n = match integer::<u64>().parse(value) {
+                    // "01937" isn't four digits, it's five
+                    Ok((_, rest)) if rest == *unit && value.len() - rest.len() > 1 && value.starts_with('0') => {
+                        return Err(format!("{:?} has a leading zero", value))
+                    }
+                    Ok((n, rest)) if rest == *unit => n,
+                    _ if unit.is_empty() => return Err(format!("{:?} isn't a number", value)),
+                    _ => return Err(format!("{:?} isn't a number of {}", value, unit)),
+                };
+                if range.contains(&n) {
+                    Ok(())
+                } else {
+                    Err(format!("{}{} is out of range", n, unit))
+                }
+            }
+            Rule::OneOf(values) => {
+                if values.contains(&value) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} isn't one of them", value))
+                }
+            }
+            Rule::Matches(pattern) => {
+                if pattern.matches_all(value) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} doesn't match", value))
+                }
+            }
+            Rule::Either(rules) => {
+                if rules.iter().any(|rule| rule.check(value).is_ok()) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} doesn't fit any of them", value))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::Anything => write!(f, "anything"),
+            Rule::Range(range, unit) => write!(f, "{}-{}{}", range.start(), range.end(), unit),
+            Rule::OneOf(values) => write!(f, "one of {}", values.join(", ")),
+            Rule::Matches(pattern) => write!(f, "`{}`", pattern.source()),
+            Rule::Either(rules) => {
+                let rules = rules.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", rules.join(" | "))
+            }
+        }
+    }
+}
+
+/// Which fields a record has to (or may) have, and what each of their values has to look like.
+/// Keys can be anything comparable, so they can be an enum as easily as a string
+/// ```
+/// use lib::validate::*;
+///
+/// let schema = Schema::new()
+///     .required("byr", Rule::range(1920..=2002))
+///     .required("hgt", Rule::measure(150..=193, "cm").or(Rule::measure(59..=76, "in")))
+///     .required("ecl", Rule::one_of(&["amb", "blu", "brn"]))
+///     .optional("pid", Rule::matches(r"\d{9}"));
+///
+/// let report = schema.check(vec![("byr", "2003"), ("hgt", "74in"), ("pid", "01234567")]);
+/// assert!(!report.is_valid());
+/// assert_eq!(report.to_string(), "\
+///     \"byr\" failed 1920-2002: 2003 is out of range\n\
+///     \"ecl\" failed one of amb, blu, brn: it's missing\n\
+///     \"pid\" failed `\\d{9}`: \"01234567\" doesn't match\n");
+///
+/// assert!(schema.check(vec![("byr", "2002"), ("hgt", "190cm"), ("ecl", "brn"), ("cid", "1")]).is_valid());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema<K> {
+    fields: Vec<(K, Rule, bool)>,
+}
+
+impl<K: Clone + PartialEq> Schema<K> {
+    pub fn new() -> Self {
+        Self { fields: Vec::new() }
+    }
+
+    /// adds a field that has to be there, and has to follow `rule`
+    pub fn required(mut self, key: K, rule: Rule) -> Self {
+        self.fields.push((key, rule, true));
+        self
+    }
+
+    /// adds a field that doesn't have to be there, but has to follow `rule` if it is
+    pub fn optional(mut self, key: K, rule: Rule) -> Self {
+        self.fields.push((key, rule, false));
+        self
+    }
+
+    /// checks some keys and values against every field in the schema. keys the schema doesn't mention are ignored,
+    /// and if a key shows up more than once, only its first value counts
+    pub fn check<'v>(&self, values: impl IntoIterator<Item = (K, &'v str)>) -> Report<'v, K> {
+        let values = values.into_iter().collect::<Vec<_>>();
+        let fields = self.fields.iter()
+            .map(|(key, rule, required)| {
+                let value = values.iter().find(|(k, _)| k == key).map(|(_, v)| *v);
+                let outcome = match value {
+                    Some(value) => rule.check(value),
+                    None if *required => Err("it's missing".to_string()),
+                    None => Ok(()),
+                };
+                FieldReport {
+                    key: key.clone(),
+                    value,
+                    outcome: outcome.map_err(|reason| Failure { rule: rule.clone(), reason }),
+                }
+            })
+            .collect();
+        Report { fields }
+    }
+}
+
+impl Schema<&'static str> {
+    /// checks a record from `lib::records` against the schema
+    pub fn check_record<'a>(&self, record: &Record<'a>) -> Report<'a, &'static str> {
+        // the schema's keys outlive the record's, so look the values up by our own keys
+        self.check(self.fields.iter().filter_map(|(key, _, _)| record.get(key).map(|v| (*key, v))))
+    }
+}
+
+impl<K: Clone + PartialEq> Default for Schema<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How each field in a `Schema` did, in the order the schema lists them
+#[derive(Debug, Clone)]
+pub struct Report<'v, K> {
+    pub fields: Vec<FieldReport<'v, K>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldReport<'v, K> {
+    pub key: K,
+    /// the field's value, if it was there at all
+    pub value: Option<&'v str>,
+    pub outcome: std::result::Result<(), Failure>,
+}
+
+/// The rule a field broke, and how it broke it
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub rule: Rule,
+    pub reason: String,
+}
+
+impl<'v, K> Report<'v, K> {
+    /// whether every field passed
+    pub fn is_valid(&self) -> bool {
+        self.fields.iter().all(|f| f.outcome.is_ok())
+    }
+
+    /// just the fields that didn't pass
+    pub fn failures(&self) -> impl Iterator<Item = (&K, &Failure)> {
+        self.fields.iter().filter_map(|f| f.outcome.as_ref().err().map(|e| (&f.key, e)))
+    }
+}
+
+/// one line per failed field, saying which rule it failed and why
+impl<'v, K: fmt::Debug> fmt::Display for Report<'v, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (key, failure) in self.failures() {
+            writeln!(f, "{:?} failed {}: {}", key, failure.rule, failure.reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::Records;
+
+    #[test]
+    fn test_rule_reasons() {
+        let height = Rule::measure(150..=193, "cm").or(Rule::measure(59..=76, "in"));
+        assert_eq!(height.to_string(), "150-193cm | 59-76in");
+        assert_eq!(height.check("76in"), Ok(()));
+        assert_eq!(height.check("77in"), Err("\"77in\" doesn't fit any of them".to_string()));
+        assert_eq!(Rule::measure(150..=193, "cm").check("194cm"), Err("194cm is out of range".to_string()));
+        assert_eq!(Rule::measure(150..=193, "cm").check("180"), Err("\"180\" isn't a number of cm".to_string()));
+        assert_eq!(Rule::matches("#[0-9a-f]{6}").check("#123abcd"), Err("\"#123abcd\" doesn't match".to_string()));
+    }
+
+    #[test]
+    fn test_rule_matches_any_alternative() {
+        assert_eq!(Rule::matches("a|ab").check("ab"), Ok(()));
+        assert_eq!(Rule::matches("in|inch").check("inch"), Ok(()));
+        assert_eq!(Rule::matches("(a|ab)c").check("abc"), Ok(()));
+        assert!(Rule::matches("a|ab").check("abc").is_err());
+        assert!(Rule::matches("a*?").check("aaa").is_ok());
+    }
+
+    #[test]
+    fn test_rule_range_is_plain_digits() {
+        let year = Rule::range(1920..=2002);
+        assert_eq!(year.check("1937"), Ok(()));
+        assert_eq!(year.check("+1937"), Err("\"+1937\" isn't a number".to_string()));
+        assert_eq!(year.check("01937"), Err("\"01937\" has a leading zero".to_string()));
+        assert_eq!(year.check("-1937"), Err("\"-1937\" isn't a number".to_string()));
+        assert_eq!(Rule::range(0..=9).check("0"), Ok(()));
+        assert_eq!(Rule::range(0..=9).check("00"), Err("\"00\" has a leading zero".to_string()));
+        assert_eq!(Rule::measure(150..=193, "cm").check("0183cm"), Err("\"0183cm\" has a leading zero".to_string()));
+        assert_eq!(Rule::measure(0..=5, "cm").check("0cm"), Ok(()));
+        assert_eq!(Rule::range(0..=9).check("0x"), Err("\"0x\" isn't a number".to_string()));
+    }
+
+    #[test]
+    fn test_check_record() {
+        let records = Records::new().read("byr:1937 iyr:2017\n\nbyr:1900").unwrap();
+        let schema = Schema::new().required("byr", Rule::range(1920..=2002)).required("iyr", Rule::Anything);
+        assert!(schema.check_record(&records[0]).is_valid());
+
+        let report = schema.check_record(&records[1]);
+        let failed = report.failures().map(|(key, failure)| (*key, failure.reason.as_str())).collect::<Vec<_>>();
+        assert_eq!(failed, vec![("byr", "1900 is out of range"), ("iyr", "it's missing")]);
+    }
+}